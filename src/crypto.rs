@@ -0,0 +1,26 @@
+//! Ethereum-compatible ECDSA primitives, backed by the runtime host functions, so that they are
+//! available to the wasm runtime too.
+
+/// Public key.
+pub type Public = ethereum_types::H512;
+/// Address, derived from the public key.
+pub type Address = ethereum_types::H160;
+/// Recoverable signature: `r`, `s` and the recovery id.
+pub type Signature = ethereum_types::H520;
+
+/// Recover public key of the message signer.
+pub fn recover(signature: &Signature, message_hash: &primitive_types::H256) -> Result<Public, &'static str> {
+	runtime_io::secp256k1_ecdsa_recover(signature.as_fixed_bytes(), message_hash.as_fixed_bytes())
+		.map(Public::from)
+		.map_err(|_| "invalid signature")
+}
+
+/// Check that the message has been signed with the key.
+pub fn verify_public(public: &Public, signature: &Signature, message_hash: &primitive_types::H256) -> Result<bool, &'static str> {
+	recover(signature, message_hash).map(|signer| signer == *public)
+}
+
+/// Address of the public key: last 20 bytes of its keccak hash.
+pub fn public_to_address(public: &Public) -> Address {
+	Address::from_slice(&runtime_io::keccak_256(public.as_bytes())[12..])
+}
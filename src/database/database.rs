@@ -1,33 +1,44 @@
+use rstd::prelude::*;
 use crate::types::EncryptedDocumentKey;
 use codec::{Encode, Decode};
 
 #[derive(Default, Debug, PartialEq, Encode, Decode)]
-pub struct PermissionDatabase {
-    permission_entries: Vec<PermissionEntry>,
-    documents: Vec<Document>
+pub struct PermissionDatabase<AccountId, BlockNumber> {
+    pub(super) permission_entries: Vec<PermissionEntry<AccountId, BlockNumber>>,
+    pub(super) documents: Vec<Document<AccountId>>
 }
 
 #[derive(Default, Debug, PartialEq, Encode, Decode, Clone)]
-struct PermissionEntry {
-    pub allowed_account: u64,
-    pub document_id: Vec<u64>
+pub(super) struct PermissionEntry<AccountId, BlockNumber> {
+    pub allowed_account: AccountId,
+    pub document_id: Vec<u64>,
+    pub expires_at: Vec<(u64, BlockNumber)>
+}
+
+impl<AccountId, BlockNumber: PartialOrd> PermissionEntry<AccountId, BlockNumber> {
+    fn is_expired(&self, document_id: u64, now: &BlockNumber) -> bool {
+        self.expires_at.iter().any(|(id, expires_at)| id == &document_id && expires_at <= now)
+    }
 }
 
 #[derive(Default, Debug, PartialEq, Encode, Decode, Clone)]
-struct Document {
+pub(super) struct Document<AccountId> {
     pub id: u64,
+    pub owners: Vec<AccountId>,
+    pub approval_threshold: u32,
     pub key: EncryptedDocumentKey
 }
 
-impl PermissionDatabase {
+impl<AccountId: PartialEq + Clone, BlockNumber: PartialOrd + Clone> PermissionDatabase<AccountId, BlockNumber> {
     pub fn new() -> Self {
         PermissionDatabase { permission_entries: Vec::new(), documents: Vec::new() }
     }
 
-    fn get_if_address_allowed(&self, address: u64, document_id: u64) -> Option<&PermissionEntry> {
-        let intermediate_list: Vec<&PermissionEntry> = self.permission_entries
+    fn get_if_address_allowed(&self, address: &AccountId, document_id: u64, now: &BlockNumber) -> Option<&PermissionEntry<AccountId, BlockNumber>> {
+        let intermediate_list: Vec<&PermissionEntry<AccountId, BlockNumber>> = self.permission_entries
             .iter()
-            .filter(|item| item.allowed_account == address && item.document_id.iter().find(|x| x == &&document_id) != None)
+            .filter(|item| &item.allowed_account == address && item.document_id.iter().find(|x| x == &&document_id) != None)
+            .filter(|item| !item.is_expired(document_id, now))
             .collect();
         if !intermediate_list.is_empty() {
             Some(intermediate_list[0])
@@ -36,47 +47,132 @@ impl PermissionDatabase {
         }
     }
 
-    pub fn get_encrypted_document_key(&self, address: u64, document_id: u64) -> Option<&EncryptedDocumentKey> {
-        let retval: Option<&EncryptedDocumentKey> = match self.get_if_address_allowed(address, document_id) {
-            None => {
+    pub fn is_address_allowed(&self, address: &AccountId, document_id: u64, now: &BlockNumber) -> bool {
+        self.is_document_owner(address, document_id)
+            || self.get_if_address_allowed(address, document_id, now).is_some()
+    }
+
+    pub fn access_expiry(&self, address: &AccountId, document_id: u64) -> Option<&BlockNumber> {
+        self.permission_entries.iter()
+            .find(|x| &x.allowed_account == address)
+            .and_then(|entry| entry.expires_at.iter().find(|(id, _)| id == &document_id))
+            .map(|(_, expires_at)| expires_at)
+    }
+
+    pub fn get_encrypted_document_key(&self, address: &AccountId, document_id: u64, now: &BlockNumber) -> Option<&EncryptedDocumentKey> {
+        let retval: Option<&EncryptedDocumentKey> = match self.is_address_allowed(address, document_id, now) {
+            false => {
                 None
             },
-            Some(entry) => {
-                self.documents.iter().find(|item| item.id == document_id).map(|item| item.key.as_ref())
+            true => {
+                self.documents.iter().find(|item| item.id == document_id).map(|item| &item.key)
             },
         };
         retval
     }
 
-    pub fn set_address_allowed(&mut self, address: u64, document_id: u64) {
-        if self.permission_entries.iter().find(|x| x.allowed_account == address) == None {
-            self.permission_entries.push(PermissionEntry {
-                allowed_account: address,
-                document_id: vec![document_id]
-            });
-        } {
-            self.permission_entries.iter_mut()
-                                   .find(|x| x.allowed_account == address)
-                                   .unwrap()
-                                   .document_id
-                                   .push(document_id);
+    pub fn document_key(&self, document_id: u64) -> Option<&EncryptedDocumentKey> {
+        self.documents.iter().find(|item| item.id == document_id).map(|item| &item.key)
+    }
+
+    pub fn document_owners(&self, document_id: u64) -> Option<&[AccountId]> {
+        self.documents.iter().find(|item| item.id == document_id).map(|item| item.owners.as_slice())
+    }
+
+    pub fn is_document_owner(&self, address: &AccountId, document_id: u64) -> bool {
+        self.document_owners(document_id).map(|owners| owners.contains(address)).unwrap_or(false)
+    }
+
+    pub fn document_approval_threshold(&self, document_id: u64) -> Option<u32> {
+        self.documents.iter().find(|item| item.id == document_id).map(|item| item.approval_threshold)
+    }
+
+    pub fn has_document(&self, document_id: u64) -> bool {
+        self.documents.iter().any(|item| item.id == document_id)
+    }
+
+    pub fn size(&self) -> usize {
+        self.documents.len() + self.permission_entries.iter()
+            .map(|entry| 1 + entry.document_id.len() + entry.expires_at.len())
+            .sum::<usize>()
+    }
+
+    pub fn set_address_allowed(&mut self, address: &AccountId, document_id: u64) {
+        let entry = self.permission_entry_mut(address);
+        if !entry.document_id.contains(&document_id) {
+            entry.document_id.push(document_id);
+        }
+        entry.expires_at.retain(|(id, _)| id != &document_id);
+    }
+
+    pub fn set_addresses_allowed(&mut self, addresses: &[AccountId], document_ids: &[u64]) {
+        for address in addresses {
+            for document_id in document_ids {
+                self.set_address_allowed(address, *document_id);
+            }
         }
     }
 
-    pub fn set_address_disallowed(&mut self, address: u64, document_id: u64) {
-        if self.permission_entries.iter().find(|x| x.allowed_account == address) != None {
-            self.permission_entries.iter_mut()
-                                   .find(|x| x.allowed_account == address)
-                                   .unwrap()
-                                   .document_id
-                                   .retain(|x| x != &document_id);
+    pub fn set_address_allowed_until(&mut self, address: &AccountId, document_id: u64, expires_at: BlockNumber) {
+        self.set_address_allowed(address, document_id);
+        self.permission_entry_mut(address).expires_at.push((document_id, expires_at));
+    }
+
+    fn permission_entry_mut(&mut self, address: &AccountId) -> &mut PermissionEntry<AccountId, BlockNumber> {
+        match self.permission_entries.iter().position(|x| &x.allowed_account == address) {
+            Some(index) => &mut self.permission_entries[index],
+            None => {
+                self.permission_entries.push(PermissionEntry {
+                    allowed_account: address.clone(),
+                    document_id: Vec::new(),
+                    expires_at: Vec::new()
+                });
+                self.permission_entries.last_mut().unwrap()
+            },
         }
     }
 
-    pub fn add_document_key_pair(&mut self, document_id: u64, document_key: EncryptedDocumentKey) {
+    pub fn set_address_disallowed(&mut self, address: &AccountId, document_id: u64) {
+        if let Some(entry) = self.permission_entries.iter_mut().find(|x| &x.allowed_account == address) {
+            entry.document_id.retain(|x| x != &document_id);
+            entry.expires_at.retain(|(id, _)| id != &document_id);
+        }
+        self.permission_entries.retain(|x| !x.document_id.is_empty());
+    }
+
+    pub fn set_addresses_disallowed(&mut self, addresses: &[AccountId], document_ids: &[u64]) {
+        for entry in self.permission_entries.iter_mut().filter(|x| addresses.contains(&x.allowed_account)) {
+            entry.document_id.retain(|x| !document_ids.contains(x));
+            entry.expires_at.retain(|(id, _)| !document_ids.contains(id));
+        }
+        self.permission_entries.retain(|x| !x.document_id.is_empty());
+    }
+
+    pub fn add_document_key_pair(&mut self, owner: &AccountId, document_id: u64, document_key: EncryptedDocumentKey) {
+        self.add_co_owned_document_key_pair(vec![owner.clone()], 1, document_id, document_key);
+    }
+
+    pub fn add_co_owned_document_key_pair(&mut self, owners: Vec<AccountId>, approval_threshold: u32, document_id: u64, document_key: EncryptedDocumentKey) {
         self.documents.push(Document {
             id: document_id,
+            owners,
+            approval_threshold,
             key: document_key
         });
     }
-}
\ No newline at end of file
+
+    pub fn set_document_key(&mut self, document_id: u64, document_key: EncryptedDocumentKey) {
+        if let Some(document) = self.documents.iter_mut().find(|item| item.id == document_id) {
+            document.key = document_key;
+        }
+    }
+
+    pub fn remove_document(&mut self, document_id: u64) {
+        self.documents.retain(|x| x.id != document_id);
+        for entry in self.permission_entries.iter_mut() {
+            entry.document_id.retain(|x| x != &document_id);
+            entry.expires_at.retain(|(id, _)| id != &document_id);
+        }
+        self.permission_entries.retain(|x| !x.document_id.is_empty());
+    }
+}
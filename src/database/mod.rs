@@ -1,3 +1,4 @@
 mod database;
+pub mod v0;
 
-pub use self::database::PermissionDatabase;
\ No newline at end of file
+pub use self::database::PermissionDatabase;
@@ -0,0 +1,55 @@
+//! Layout of the permission database before documents got owners and permissions got expiries.
+
+use rstd::prelude::*;
+use crate::types::EncryptedDocumentKey;
+use codec::{Encode, Decode};
+
+#[derive(Default, Debug, PartialEq, Encode, Decode)]
+pub struct PermissionDatabase {
+    pub permission_entries: Vec<PermissionEntry>,
+    pub documents: Vec<Document>
+}
+
+#[derive(Default, Debug, PartialEq, Encode, Decode, Clone)]
+pub struct PermissionEntry {
+    pub allowed_account: u64,
+    pub document_id: Vec<u64>
+}
+
+#[derive(Default, Debug, PartialEq, Encode, Decode, Clone)]
+pub struct Document {
+    pub id: u64,
+    pub key: EncryptedDocumentKey
+}
+
+impl PermissionDatabase {
+    /// Convert into the current layout. Documents have had no owners, and accounts, which have been
+    /// allowed to retrieve them, are only readers, so documents are left without owners: their permissions
+    /// could only be changed by the administrators. Returns the first account, which `convert_account`
+    /// fails to convert.
+    pub fn migrate<AccountId, BlockNumber>(
+        self,
+        convert_account: impl Fn(u64) -> Option<AccountId>
+    ) -> Result<super::PermissionDatabase<AccountId, BlockNumber>, u64> {
+        let permission_entries = self.permission_entries.into_iter()
+            .map(|entry| {
+                let allowed_account = convert_account(entry.allowed_account).ok_or(entry.allowed_account)?;
+                Ok(super::database::PermissionEntry {
+                    allowed_account,
+                    document_id: entry.document_id,
+                    expires_at: Vec::new()
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let documents = self.documents.into_iter()
+            .map(|document| super::database::Document {
+                id: document.id,
+                owners: Vec::new(),
+                approval_threshold: u32::max_value(),
+                key: document.key
+            })
+            .collect();
+
+        Ok(super::PermissionDatabase { permission_entries, documents })
+    }
+}
@@ -1,3 +1,5 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+
 /// A runtime module template with necessary imports
 
 /// Feel free to remove or edit this file as needed.
@@ -8,16 +10,40 @@
 /// For more guidance on Substrate modules, see the example module
 /// https://github.com/paritytech/substrate/blob/master/srml/example/src/lib.rs
 
-extern crate parity_crypto as crypto;
+#[cfg(not(feature = "std"))]
+extern crate alloc;
 extern crate parity_bytes as bytes;
 use codec::{Encode, Decode};
-mod database;
-mod types;
+pub mod crypto;
+pub mod database;
+pub mod migration;
+pub mod types;
+pub mod runtime_api;
+pub mod weights;
 
-// use database::PermissionDatabase;
+use database::PermissionDatabase;
 
-use support::{decl_module, decl_storage, decl_event, dispatch::Result};
+use rstd::prelude::*;
+use rstd::result;
+use sr_primitives::traits::{Zero, One, Convert};
+use primitives::crypto::UncheckedFrom;
+use crypto::Address;
+use support::{decl_module, decl_storage, decl_event, decl_error, ensure};
+use support::traits::{Currency, ReservableCurrency, OnUnbalanced, EnsureOrigin};
+use support::weights::{SimpleDispatchInfo, GetDispatchInfo, DispatchClass};
 use system::ensure_signed;
+use weights::{WeightOf, ClassifiedWeightOf, WithPermissions};
+use types::{
+	NodeId, MessageHash, Public, ServerKeyId, EncryptedDocumentKey, EncryptedDocumentKeyShadow, AdminPolicy,
+	RequestSignature, Requester, ReplayCache, TypedDataDomain,
+};
+
+/// Result of the module dispatchables.
+pub type Result = result::Result<(), Error>;
+
+type BalanceOf<T> = <<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::Balance;
+type NegativeImbalanceOf<T> =
+	<<T as Trait>::Currency as Currency<<T as system::Trait>::AccountId>>::NegativeImbalance;
 
 /// The module's configuration trait.
 pub trait Trait: system::Trait {
@@ -26,6 +52,354 @@ pub trait Trait: system::Trait {
 	/// The overarching event type.
 	type Event: From<Event<Self>> + Into<<Self as system::Trait>::Event>;
 	type EncryptedDocumentKey: Encode + Decode;
+	/// The currency in which key server bonds are held.
+	type Currency: ReservableCurrency<Self::AccountId>;
+	/// Handler for the bonds slashed from misbehaving key servers.
+	type Slash: OnUnbalanced<NegativeImbalanceOf<Self>>;
+	/// Local key server, servicing pending requests from the off-chain worker.
+	type KeyServer: KeyServer;
+	/// Verifier of the messages, reported as key server misbehaviour.
+	type VerifyMisbehaviour: VerifyMisbehaviour;
+	/// Submitter of the signed key server responses.
+	type SubmitResponse: SubmitResponse<Self>;
+	/// Origin, which is allowed to perform privileged operations on documents and the servers set.
+	type AdminOrigin: EnsureOrigin<Self::Origin>;
+	/// Conversion of the substrate requester public (sr25519 or ed25519) to the account, checked by the ACL.
+	type RequesterAccount: Convert<[u8; 32], Self::AccountId>;
+}
+
+/// Requester account conversion for runtimes, which use substrate publics as account ids.
+pub struct PublicAccount;
+
+impl<AccountId: UncheckedFrom<[u8; 32]>> Convert<[u8; 32], AccountId> for PublicAccount {
+	fn convert(public: [u8; 32]) -> AccountId {
+		AccountId::unchecked_from(public)
+	}
+}
+
+/// Local key server, which is servicing secret store requests on behalf of the off-chain worker.
+pub trait KeyServer {
+	/// Public key of the local key server node. None if this node does not run a key server.
+	fn node_id() -> Option<NodeId>;
+	/// Generate new server key with the given threshold.
+	fn generate_server_key(key_id: &ServerKeyId, threshold: u8) -> result::Result<Public, types::Error>;
+	/// Retrieve previously generated server key and its threshold.
+	fn restore_server_key(key_id: &ServerKeyId) -> result::Result<(Public, u8), types::Error>;
+	/// Retrieve document key shadow, encrypted with the requester public key.
+	fn restore_document_key_shadow(
+		key_id: &ServerKeyId,
+		requester: &Public,
+	) -> result::Result<EncryptedDocumentKeyShadow, types::Error>;
+	/// Hashes of the pending requests, which have already been serviced by the local key server.
+	fn serviced_requests() -> Vec<MessageHash>;
+	/// Remember hashes of the pending requests, which have been serviced by the local key server.
+	fn set_serviced_requests(requests: Vec<MessageHash>);
+	/// Signed requests, which have already been accepted by the local key server.
+	fn replay_cache() -> ReplayCache;
+	/// Remember signed requests, which have been accepted by the local key server.
+	fn set_replay_cache(cache: ReplayCache);
+}
+
+/// Verifier of the messages, reported as key server misbehaviour.
+pub trait VerifyMisbehaviour {
+	/// Check that the message, sent by the key server, is actually invalid.
+	fn is_invalid(kind: &MisbehaviourKind, message: &[u8]) -> bool;
+}
+
+/// Submitter of the key server responses, produced by the off-chain worker.
+pub trait SubmitResponse<T: Trait> {
+	/// Sign the response with the key server account and submit it to the transaction pool.
+	fn submit_response(response: Call<T>) -> result::Result<(), ()>;
+}
+
+/// Request, waiting to be serviced by the key servers.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum PendingRequest<AccountId> {
+	/// Server key generation request.
+	ServerKeyGeneration {
+		/// Document the server key is generated for.
+		document_id: u64,
+		/// Request author.
+		author: AccountId,
+		/// Server key threshold.
+		threshold: u8,
+	},
+	/// Server key retrieval request.
+	ServerKeyRetrieval {
+		/// Document the server key has been generated for.
+		document_id: u64,
+	},
+	/// Document key shadow retrieval request.
+	DocumentKeyShadowRetrieval {
+		/// Document the key is requested for.
+		document_id: u64,
+		/// Request author.
+		requester: AccountId,
+		/// Public key the decryption shadows must be encrypted with.
+		requester_public: Public,
+	},
+}
+
+impl<AccountId> PendingRequest<AccountId> {
+	/// Document the request is filed for.
+	pub fn document_id(&self) -> u64 {
+		match *self {
+			PendingRequest::ServerKeyGeneration { document_id, .. } |
+			PendingRequest::ServerKeyRetrieval { document_id } |
+			PendingRequest::DocumentKeyShadowRetrieval { document_id, .. } => document_id,
+		}
+	}
+}
+
+impl<AccountId: Encode> PendingRequest<AccountId> {
+	/// Hash of the request, identifying it while it is pending.
+	pub fn hash(&self) -> MessageHash {
+		runtime_io::blake2_256(&self.encode()).into()
+	}
+}
+
+/// Author and expiry of the pending request.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RequestFiling<AccountId, BlockNumber> {
+	/// Account, which has filed the request.
+	pub author: AccountId,
+	/// Block at which the request expires, unless serviced by the key servers.
+	pub expires_at: BlockNumber,
+}
+
+/// Server key, generated by the key servers.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ServerKey {
+	/// Server key public.
+	pub public: Public,
+	/// Server key threshold.
+	pub threshold: u8,
+}
+
+/// Change of the co-owned document, which must be approved by its owners.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum DocumentAction<AccountId> {
+	/// Allow the account to retrieve the document key.
+	Grant(AccountId),
+	/// Disallow the account to retrieve the document key.
+	Revoke(AccountId),
+	/// Delete the document key along with all permissions to retrieve it.
+	Delete,
+}
+
+/// Proposed change of the co-owned document.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Proposal<AccountId, BlockNumber> {
+	/// Proposed change.
+	pub action: DocumentAction<AccountId>,
+	/// Owners that have approved the change.
+	pub approvals: Vec<AccountId>,
+	/// Block at which the proposal expires, unless approved by enough owners.
+	pub expires_at: BlockNumber,
+}
+
+/// Time lock of the document key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ReleaseSchedule<AccountId, BlockNumber> {
+	/// Block, starting from which the document key could be retrieved. Until then, only owners have access.
+	pub release_at: BlockNumber,
+	/// Accounts, which are allowed to retrieve the key once it is released. None means everyone.
+	pub beneficiaries: Option<Vec<AccountId>>,
+}
+
+/// Dead-man's switch of the document key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Inheritance<AccountId, BlockNumber> {
+	/// Owner, who must regularly check in to keep the switch from triggering.
+	pub owner: AccountId,
+	/// Accounts, which gain access to the document key once the switch is triggered.
+	pub beneficiaries: Vec<AccountId>,
+	/// Number of blocks without owner check-in, after which the switch is triggered.
+	pub inactivity_period: BlockNumber,
+}
+
+/// Kind of the access to the document, recorded in the audit trail.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum AuditKind {
+	/// Encrypted document key has been retrieved.
+	DocumentKeyRetrieved,
+	/// Document key shadow has been requested from the key servers.
+	DocumentKeyShadowRequested,
+	/// Document key shadow has been released by the key servers.
+	DocumentKeyShadowReleased,
+	/// Emergency access to the document has been claimed.
+	EmergencyAccessClaimed,
+	/// Emergency access claim has been vetoed by the owner.
+	EmergencyAccessVetoed,
+	/// Emergency access has been granted after the challenge delay.
+	EmergencyAccessGranted,
+}
+
+/// Audit trail record.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct AuditEntry<AccountId, BlockNumber> {
+	/// Account, which has accessed the document.
+	pub account: AccountId,
+	/// Block at which the document has been accessed.
+	pub block_number: BlockNumber,
+	/// Kind of the access.
+	pub kind: AuditKind,
+}
+
+/// Offer to sell access to the document key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Listing<AccountId, Balance> {
+	/// Owner, receiving the payments.
+	pub seller: AccountId,
+	/// Price of the access.
+	pub price: Balance,
+}
+
+/// Purchased access, which payment is held in escrow until the buyer receives the key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Purchase<AccountId, Balance> {
+	/// Owner, receiving the payment.
+	pub seller: AccountId,
+	/// Payment, reserved on the buyer account.
+	pub price: Balance,
+}
+
+/// Offer to subscribe to the document key access.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct SubscriptionPlan<AccountId, Balance, BlockNumber> {
+	/// Owner, receiving the payments.
+	pub seller: AccountId,
+	/// Price of the single billing period.
+	pub price: Balance,
+	/// Length of the billing period, in blocks.
+	pub period: BlockNumber,
+}
+
+/// Active subscription to the document key access.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Subscription<AccountId, Balance, BlockNumber> {
+	/// Plan, agreed by the subscriber. The subscription lapses once the plan is changed or withdrawn.
+	pub plan: SubscriptionPlan<AccountId, Balance, BlockNumber>,
+	/// Block at which the subscription must be renewed.
+	pub renews_at: BlockNumber,
+}
+
+/// Request of the account to access the document key.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct AccessRequest<BlockNumber> {
+	/// Hash of the message, attached to the request.
+	pub message_hash: Option<MessageHash>,
+	/// Block at which the request expires, unless approved or rejected by the owner.
+	pub expires_at: BlockNumber,
+	/// Index of the grant proposal of the co-owned document, approving the request. The proposal is
+	/// dropped along with the request.
+	pub proposal: Option<u32>,
+}
+
+/// Key server, registered by the account.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct BondedKeyServer<Balance> {
+	/// Public key of the key server node.
+	pub node_id: NodeId,
+	/// Funds, reserved on the owner account while the key server is in the set.
+	pub bond: Balance,
+}
+
+/// Bond of the key server, which has left the set. It could still be slashed until it is unlocked.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct UnbondingKeyServer<Balance, BlockNumber> {
+	/// Public key of the key server node.
+	pub node_id: NodeId,
+	/// Funds, reserved on the owner account until the unbonding period ends.
+	pub bond: Balance,
+	/// Block, starting from which the bond could be withdrawn.
+	pub unlocks_at: BlockNumber,
+}
+
+/// Kind of the key server misbehaviour.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum MisbehaviourKind {
+	/// Key server has sent a message that was recognized as invalid.
+	InvalidMessage,
+	/// Key server has sent an invalid decryption shadow.
+	InvalidShadow,
+}
+
+/// Evidence of the key server misbehaviour, submitted by its peers.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Misbehaviour {
+	/// Kind of the misbehaviour.
+	pub kind: MisbehaviourKind,
+	/// The offending message.
+	pub message: Vec<u8>,
+	/// Offender signature of the message hash, proving that the message has been sent by the offender.
+	pub signature: Vec<u8>,
+}
+
+impl Misbehaviour {
+	/// Hash of the key server message, which is signed by the sender. Separated from the other
+	/// signed hashes, so that e.g. heartbeat signatures can't be presented as misbehaviour evidence.
+	pub fn message_hash(message: &[u8]) -> MessageHash {
+		runtime_io::blake2_256(&(&b"secret store key server message"[..], message).encode()).into()
+	}
+
+	/// Check that the offending message has been signed by the given node.
+	pub fn is_signed_by(&self, node_id: &NodeId) -> bool {
+		is_signed_by_key(node_id, &self.signature, &Self::message_hash(&self.message))
+	}
+}
+
+/// Liveness proof, periodically submitted on behalf of the key server.
+#[derive(Encode, Decode, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Heartbeat<BlockNumber> {
+	/// Public key of the key server node.
+	pub node_id: NodeId,
+	/// Block at which the heartbeat has been produced.
+	pub block_number: BlockNumber,
+}
+
+impl<BlockNumber: Encode> Heartbeat<BlockNumber> {
+	/// Hash of the heartbeat, which must be signed by the key server node.
+	pub fn hash(&self) -> MessageHash {
+		runtime_io::blake2_256(&(&b"secret store heartbeat"[..], self).encode()).into()
+	}
+}
+
+/// Parse the serialized secp256k1 signature.
+fn parse_signature(signature: &[u8]) -> Option<RequestSignature> {
+	if signature.len() != 65 {
+		return None;
+	}
+
+	let mut raw_signature = [0u8; 65];
+	raw_signature.copy_from_slice(signature);
+	Some(raw_signature.into())
+}
+
+/// Check that the message hash has been signed with the key.
+fn is_signed_by_key(public: &Public, signature: &[u8], message_hash: &MessageHash) -> bool {
+	parse_signature(signature)
+		.and_then(|signature| crypto::verify_public(public, &signature, message_hash).ok())
+		.unwrap_or(false)
 }
 
 // This module's storage items.
@@ -35,6 +409,299 @@ decl_storage! {
 		// Here we are declaring a StorageValue, `Something` as a Option<u32>
 		// `get(something)` is the default getter which returns either the stored `u32` or `None` if nothing stored
 		Something get(something): Option<u32>;
+
+		/// Funds that must be bonded to become a key server.
+		KeyServerBond get(key_server_bond) config(): BalanceOf<T>;
+		/// Number of distinct peers that must report a key server before it is slashed.
+		MisbehaviourReportThreshold get(misbehaviour_report_threshold) config()
+			build(|config: &GenesisConfig<T>| {
+				assert!(config.misbehaviour_report_threshold != 0, "misbehaviour report threshold must be nonzero");
+				config.misbehaviour_report_threshold
+			}): u32;
+		/// Node ids of all key servers in the set.
+		KeyServers get(key_servers): Vec<NodeId>;
+		/// Key servers, registered by accounts.
+		Bonds get(bonded_key_server): map T::AccountId => Option<BondedKeyServer<BalanceOf<T>>>;
+		/// Number of blocks the bond stays reserved (and could be slashed) after the key server leaves the set.
+		UnbondingPeriod get(unbonding_period) config(): T::BlockNumber;
+		/// Bonds of the key servers, which have left the set, by owner account.
+		Unbonding get(unbonding_key_server): map T::AccountId => Option<UnbondingKeyServer<BalanceOf<T>, T::BlockNumber>>;
+		/// Accounts that have registered key servers.
+		KeyServerOwners get(key_server_owner): map NodeId => Option<T::AccountId>;
+		/// Accounts that have reported the key server misbehaviour.
+		MisbehaviourReports get(misbehaviour_reports): map NodeId => Vec<T::AccountId>;
+		/// Number of blocks without heartbeat after which the key server is considered offline.
+		HeartbeatTimeout get(heartbeat_timeout) config(): T::BlockNumber;
+		/// Block of the last heartbeat, received from the key server.
+		LastSeen get(last_seen): map NodeId => Option<T::BlockNumber>;
+		/// Key servers, which must be checked for missed heartbeats at the block.
+		HeartbeatDeadlines get(heartbeat_deadlines): map T::BlockNumber => Vec<NodeId>;
+		/// Node ids of key servers that have missed their heartbeats.
+		OfflineKeyServers get(offline_key_servers): Vec<NodeId>;
+
+		/// Version of the storage layout. Chains, started with this code, have the current layout.
+		StorageVersion get(storage_version) build(|_| migration::CURRENT_STORAGE_VERSION): u32;
+		/// Storage migration has failed, and is not retried.
+		MigrationFailed get(migration_failed): bool;
+		/// Stored document keys and the access control list.
+		Permissions get(permissions): PermissionDatabase<T::AccountId, T::BlockNumber>;
+		/// Size of the permissions database, which every access to the database pays for.
+		PermissionsSize get(permissions_size): u32;
+		/// Requests, waiting to be serviced by the key servers.
+		PendingRequests get(pending_requests): Vec<PendingRequest<T::AccountId>>;
+		/// Maximal number of requests, waiting to be serviced by the key servers.
+		MaxPendingRequests get(max_pending_requests) config(): u32;
+		/// Maximal number of requests of the single account, waiting to be serviced by the key servers.
+		MaxAccountPendingRequests get(max_account_pending_requests) config(): u32;
+		/// Number of blocks the request stays pending before it expires.
+		RequestTtl get(request_ttl) config(): T::BlockNumber;
+		/// Authors and expiries of the pending requests, by request hash.
+		RequestFilings get(request_filing): map MessageHash => Option<RequestFiling<T::AccountId, T::BlockNumber>>;
+		/// Number of pending requests, filed by the account.
+		AccountPendingRequests get(account_pending_requests): map T::AccountId => u32;
+		/// Pending requests (by hash), expiring at the block.
+		RequestExpiries get(request_expiries): map T::BlockNumber => Vec<MessageHash>;
+		/// Key servers, which have submitted the response to the pending request, by request and response hash.
+		ResponseVotes get(response_votes): map (MessageHash, MessageHash) => Vec<NodeId>;
+		/// Hashes of the distinct responses, submitted to the pending request.
+		RequestResponses get(request_responses): map MessageHash => Vec<MessageHash>;
+		/// Server keys, generated for documents.
+		ServerKeys get(server_key): map u64 => Option<ServerKey>;
+		/// Documents, frozen by the administrator.
+		FrozenDocuments get(is_document_frozen): map u64 => bool;
+		/// M-of-N administrators policy, authorizing admin operations with multiple signatures.
+		Administrators get(admin_policy): Option<AdminPolicy>;
+		/// Number of admin operations, executed with administrators signatures. Protects them from replay.
+		AdminNonce get(admin_nonce): u64;
+		/// Emergency switch, halting release of all document keys and shadows.
+		KeyReleasesFrozen get(key_releases_frozen): bool;
+
+		/// Number of blocks the access request stays pending before it expires.
+		AccessRequestTtl get(access_request_ttl) config(): T::BlockNumber;
+		/// Pending requests to access documents.
+		AccessRequests get(access_request): map (u64, T::AccountId) => Option<AccessRequest<T::BlockNumber>>;
+		/// Access requests, expiring at the block.
+		AccessRequestExpiries get(access_request_expiries): map T::BlockNumber => Vec<(u64, T::AccountId)>;
+
+		/// Number of blocks the proposal stays open before it expires.
+		ProposalTtl get(proposal_ttl) config(): T::BlockNumber;
+		/// Proposed changes of co-owned documents, by document and proposal index.
+		Proposals get(proposal): double_map u64, blake2_256(u32) => Option<Proposal<T::AccountId, T::BlockNumber>>;
+		/// Number of proposals, ever created for the document.
+		ProposalCount get(proposal_count): map u64 => u32;
+		/// Number of open proposals of the document.
+		OpenProposals get(open_proposals): map u64 => u32;
+		/// Proposals (by document and index), expiring at the block.
+		ProposalExpiries get(proposal_expiries): map T::BlockNumber => Vec<(u64, u32)>;
+
+		/// Time locks of the documents.
+		ReleaseSchedules get(release_schedule): map u64 => Option<ReleaseSchedule<T::AccountId, T::BlockNumber>>;
+		/// Documents, which time locks open at the block.
+		ScheduledReleases get(scheduled_releases): map T::BlockNumber => Vec<u64>;
+
+		/// Block of the last check-in of the account.
+		LastCheckIn get(last_check_in): map T::AccountId => Option<T::BlockNumber>;
+		/// Dead-man's switches of the documents.
+		Inheritances get(inheritance): map u64 => Option<Inheritance<T::AccountId, T::BlockNumber>>;
+
+		/// Number of blocks the owner has to veto the emergency access claim.
+		EmergencyAccessDelay get(emergency_access_delay) config(): T::BlockNumber;
+		/// Accounts, which could claim emergency access to the document.
+		EmergencyAccounts get(emergency_accounts): map u64 => Vec<T::AccountId>;
+		/// Pending emergency access claims and blocks, starting from which they could be completed.
+		EmergencyClaims get(emergency_claim): map (u64, T::AccountId) => Option<T::BlockNumber>;
+		/// Maximal number of the most recent audit trail records, kept for every document.
+		AuditRetention get(audit_retention) config(): u32;
+		/// Access events of the documents, made through the module dispatchables (reads through the runtime
+		/// API are not recorded). Records are only appended, the oldest are dropped once there are more
+		/// than `AuditRetention` of them.
+		AuditTrail get(audit_trail): map u64 => Vec<AuditEntry<T::AccountId, T::BlockNumber>>;
+
+		/// Documents, which access is offered for sale.
+		Listings get(listing): map u64 => Option<Listing<T::AccountId, BalanceOf<T>>>;
+		/// Purchases, waiting for the buyer to retrieve the key.
+		Purchases get(purchase): map (u64, T::AccountId) => Option<Purchase<T::AccountId, BalanceOf<T>>>;
+		/// Buyers with purchases of the document, waiting for settlement.
+		DocumentBuyers get(document_buyers): map u64 => Vec<T::AccountId>;
+
+		/// Documents, which access is offered by subscription.
+		SubscriptionPlans get(subscription_plan): map u64 => Option<SubscriptionPlan<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+		/// Active subscriptions.
+		Subscriptions get(subscription): map (u64, T::AccountId) => Option<Subscription<T::AccountId, BalanceOf<T>, T::BlockNumber>>;
+		/// Subscriptions, which must be renewed at the block.
+		SubscriptionRenewals get(subscription_renewals): map T::BlockNumber => Vec<(u64, T::AccountId)>;
+
+		/// Published public keys of the addresses, used to encrypt data for address-only requesters.
+		AddressPublics get(address_public): map Address => Option<Public>;
+
+		/// Maximal number of encryption keys of the account.
+		MaxEncryptionKeys get(max_encryption_keys) config(): u32;
+		/// Public keys, used to encrypt data for the account (one per device of the account).
+		EncryptionKeys get(encryption_keys): map T::AccountId => Vec<Public>;
+	}
+}
+
+decl_error! {
+	/// Error for the secret store module.
+	pub enum Error {
+		/// Account already operates a key server.
+		AlreadyKeyServer,
+		/// Bond of the previous key server of the account is still unbonding.
+		StillUnbonding,
+		/// Account has no bond to withdraw.
+		NotUnbonding,
+		/// Unbonding period has not passed yet.
+		BondLocked,
+		/// Key server with this node id is already registered.
+		KeyServerAlreadyRegistered,
+		/// Not enough free funds to bond the key server.
+		InsufficientBond,
+		/// Account does not operate a key server.
+		NotKeyServer,
+		/// Key server is offline.
+		KeyServerOffline,
+		/// Key server cannot report itself.
+		SelfReport,
+		/// Offender is not a registered key server.
+		UnknownOffender,
+		/// Evidence is not signed by the offender.
+		InvalidEvidence,
+		/// Misbehaviour is already reported by this key server.
+		DuplicateReport,
+		/// Heartbeat is not sent by the key server owner.
+		NotKeyServerOwner,
+		/// Heartbeat is not signed by the key server.
+		InvalidHeartbeatSignature,
+		/// Heartbeat is produced at the future block.
+		FutureHeartbeat,
+		/// Heartbeat is outdated.
+		OutdatedHeartbeat,
+		/// Access to the document key is denied.
+		AccessDenied,
+		/// Release of the document key has been frozen by administrators.
+		KeyReleaseFrozen,
+		/// Document key with this ID is already stored.
+		DocumentKeyAlreadyStored,
+		/// Document key with this ID is not found.
+		DocumentKeyIsNotFound,
+		/// Server key with this ID is already generated.
+		ServerKeyAlreadyGenerated,
+		/// Server key with this ID is not found.
+		ServerKeyIsNotFound,
+		/// Key server has failed to process the request.
+		KeyServerError,
+		/// Only the document owner can perform this operation.
+		NotDocumentOwner,
+		/// Document is co-owned: the change must be approved by its owners.
+		CoOwnedDocument,
+		/// Document must be co-owned by the sender.
+		SenderNotCoOwner,
+		/// Document owner is listed twice.
+		DuplicateOwner,
+		/// Public key of this address is already published.
+		PublicAlreadyPublished,
+		/// Encryption key possession proof is not signed with the key.
+		InvalidEncryptionKeyProof,
+		/// Encryption key is already added to the account.
+		EncryptionKeyAlreadyAdded,
+		/// Encryption key is not found.
+		EncryptionKeyNotFound,
+		/// Account has too many encryption keys.
+		TooManyEncryptionKeys,
+		/// Owners approval threshold is zero or exceeds the number of owners.
+		InvalidApprovalThreshold,
+		/// Proposal is not found.
+		ProposalNotFound,
+		/// Proposal is already approved by this owner.
+		AlreadyApproved,
+		/// Document is frozen.
+		DocumentFrozen,
+		/// Document is already frozen.
+		DocumentAlreadyFrozen,
+		/// Document is not frozen.
+		DocumentNotFrozen,
+		/// Access is already granted.
+		AccessAlreadyGranted,
+		/// Access is already requested.
+		AccessAlreadyRequested,
+		/// No pending access request.
+		NoAccessRequest,
+		/// Access must expire in the future.
+		AccessExpiryInPast,
+		/// Document key is already time locked.
+		AlreadyTimeLocked,
+		/// Release block must be in the future.
+		ReleaseInPast,
+		/// Inheritance requires at least one beneficiary.
+		NoBeneficiaries,
+		/// Inactivity period must be positive.
+		ZeroInactivityPeriod,
+		/// Document has no inheritance.
+		NoInheritance,
+		/// Account can't claim emergency access.
+		NotEmergencyAccount,
+		/// Emergency access is already claimed.
+		EmergencyAccessAlreadyClaimed,
+		/// No pending emergency access claim.
+		NoEmergencyClaim,
+		/// Emergency access challenge delay has not passed.
+		EmergencyAccessDelayNotPassed,
+		/// Document is not listed.
+		NotListed,
+		/// Not enough free funds to pay for access.
+		InsufficientFunds,
+		/// No pending purchase.
+		NoPendingPurchase,
+		/// Purchased access could be used right now, so the purchase can't be cancelled.
+		PurchaseUsable,
+		/// Billing period must be positive.
+		ZeroBillingPeriod,
+		/// Document has no subscription offer.
+		NoSubscriptionOffer,
+		/// Account is already subscribed.
+		AlreadySubscribed,
+		/// Account is not subscribed.
+		NotSubscribed,
+		/// The same request is already pending.
+		RequestAlreadyPending,
+		/// No matching pending request.
+		NoPendingRequest,
+		/// Too many requests are waiting to be serviced by the key servers.
+		TooManyPendingRequests,
+		/// Too many requests of the account are waiting to be serviced by the key servers.
+		TooManyAccountPendingRequests,
+		/// Key server has already responded to the request.
+		AlreadyResponded,
+		/// Multisig administration is disabled.
+		NoAdminPolicy,
+		/// Not enough administrators signatures.
+		NotEnoughAdminSignatures,
+		/// Call is not an admin operation.
+		NotAdminCall,
+		/// Invalid administrators policy.
+		InvalidAdminPolicy,
+		/// Key releases are already frozen.
+		KeyReleasesAlreadyFrozen,
+		/// Key releases are not frozen.
+		KeyReleasesNotFrozen,
+		/// Misbehaviour report threshold must be positive.
+		ZeroReportThreshold,
+		/// Storage has not been migrated to the layout, used by this code.
+		StorageNotMigrated,
+	}
+}
+
+impl From<types::Error> for Error {
+	fn from(error: types::Error) -> Self {
+		match error {
+			types::Error::AccessDenied => Error::AccessDenied,
+			types::Error::KeyReleaseFrozen => Error::KeyReleaseFrozen,
+			types::Error::DocumentKeyAlreadyStored => Error::DocumentKeyAlreadyStored,
+			types::Error::DocumentKeyIsNotFound => Error::DocumentKeyIsNotFound,
+			types::Error::ServerKeyAlreadyGenerated => Error::ServerKeyAlreadyGenerated,
+			types::Error::ServerKeyIsNotFound => Error::ServerKeyIsNotFound,
+			_ => Error::KeyServerError,
+		}
 	}
 }
 
@@ -46,9 +713,12 @@ decl_module! {
 		// this is needed only if you are using events in your module
 		fn deposit_event() = default;
 
+		type Error = Error;
+
 		// Just a dummy entry point.
 		// function that can be called by the external world as an extrinsics call
 		// takes a parameter of the type `AccountId`, stores it and emits an event
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::DO_SOMETHING)]
 		pub fn do_something(origin, something: u32) -> Result {
 			// TODO: You only need this if you want to check it was signed.
 			let who = ensure_signed(origin)?;
@@ -59,83 +729,4247 @@ decl_module! {
 			Self::deposit_event(RawEvent::SomethingStored(something, who));
 			Ok(())
 		}
-	}
-}
 
-decl_event!(
-	pub enum Event<T> where AccountId = <T as system::Trait>::AccountId {
-		// Just a dummy event.
-		// Event `Something` is declared with a parameter of the type `u32` and `AccountId`
-		// To emit this event, we call the deposit funtion, from our runtime funtions
-		SomethingStored(u32, AccountId),
-	}
-);
+		/// Register a key server with the given node id, bonding `KeyServerBond` funds of the sender.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::KEY_SERVER_MANAGEMENT)]
+		pub fn register_key_server(origin, node_id: NodeId) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(!<Bonds<T>>::exists(&who), Error::AlreadyKeyServer);
+			ensure!(!<Unbonding<T>>::exists(&who), Error::StillUnbonding);
+			ensure!(!<KeyServerOwners<T>>::exists(&node_id), Error::KeyServerAlreadyRegistered);
 
-/// tests for this module
-#[cfg(test)]
-mod tests {
-	use super::*;
+			let bond = Self::key_server_bond();
+			T::Currency::reserve(&who, bond).map_err(|_| Error::InsufficientBond)?;
 
-	use runtime_io::with_externalities;
-	use primitives::{H256, Blake2Hasher};
-	use support::{impl_outer_origin, assert_ok, parameter_types};
-	use sr_primitives::{traits::{BlakeTwo256, IdentityLookup}, testing::Header};
-	use sr_primitives::weights::Weight;
-	use sr_primitives::Perbill;
+			<Bonds<T>>::insert(&who, BondedKeyServer { node_id: node_id.clone(), bond });
+			<KeyServerOwners<T>>::insert(&node_id, &who);
+			KeyServers::mutate(|servers| servers.push(node_id.clone()));
+			<LastSeen<T>>::insert(&node_id, <system::Module<T>>::block_number());
+			Self::schedule_heartbeat_deadline(&node_id, <system::Module<T>>::block_number());
 
-	impl_outer_origin! {
-		pub enum Origin for Test {}
-	}
+			Self::deposit_event(RawEvent::KeyServerRegistered(who, node_id));
+			Ok(())
+		}
 
-	// For testing the module, we construct most of a mock runtime. This means
-	// first constructing a configuration type (`Test`) which `impl`s each of the
-	// configuration traits of modules we want to use.
-	#[derive(Clone, Eq, PartialEq)]
-	pub struct Test;
-	parameter_types! {
-		pub const BlockHashCount: u64 = 250;
-		pub const MaximumBlockWeight: Weight = 1024;
-		pub const MaximumBlockLength: u32 = 2 * 1024;
-		pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
-	}
-	impl system::Trait for Test {
-		type Origin = Origin;
-		type Call = ();
-		type Index = u64;
-		type BlockNumber = u64;
-		type Hash = H256;
-		type Hashing = BlakeTwo256;
-		type AccountId = u64;
-		type Lookup = IdentityLookup<Self::AccountId>;
-		type Header = Header;
-		type WeightMultiplierUpdate = ();
-		type Event = ();
-		type BlockHashCount = BlockHashCount;
-		type MaximumBlockWeight = MaximumBlockWeight;
-		type MaximumBlockLength = MaximumBlockLength;
-		type AvailableBlockRatio = AvailableBlockRatio;
-		type Version = ();
-	}
-	impl Trait for Test {
-		type Event = ();
-		type EncryptedDocumentKey = bytes::Bytes;
-	}
-	type TemplateModule = Module<Test>;
+		/// Remove the sender key server from the set. The bond stays reserved for `UnbondingPeriod` blocks,
+		/// so that the key server could still be slashed for the misbehaviour, reported in the meantime.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::KEY_SERVER_MANAGEMENT)]
+		pub fn unregister_key_server(origin) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let server = <Bonds<T>>::take(&who).ok_or(Error::NotKeyServer)?;
 
-	// This function basically just builds a genesis storage key/value store according to
-	// our desired mockup.
-	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
-		system::GenesisConfig::default().build_storage::<Test>().unwrap().into()
-	}
+			let unlocks_at = <system::Module<T>>::block_number() + Self::unbonding_period();
+			<Unbonding<T>>::insert(&who, UnbondingKeyServer { node_id: server.node_id.clone(), bond: server.bond, unlocks_at });
+			Self::remove_key_server(&server.node_id);
 
-	#[test]
-	fn it_works_for_default_value() {
-		with_externalities(&mut new_test_ext(), || {
-			// Just a dummy test for the dummy funtion `do_something`
-			// calling the `do_something` function with a value 42
-			assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
-			// asserting that the stored value is equal to what we stored
-			assert_eq!(TemplateModule::something(), Some(42));
-		});
+			Self::deposit_event(RawEvent::KeyServerUnregistered(who, server.node_id, unlocks_at));
+			Ok(())
+		}
+
+		/// Release the bond of the sender key server once its unbonding period has passed.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::KEY_SERVER_MANAGEMENT)]
+		pub fn withdraw_bond(origin) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let server = Self::unbonding_key_server(&who).ok_or(Error::NotUnbonding)?;
+			ensure!(<system::Module<T>>::block_number() >= server.unlocks_at, Error::BondLocked);
+
+			<Unbonding<T>>::remove(&who);
+			T::Currency::unreserve(&who, server.bond);
+			Self::forget_key_server(&server.node_id);
+
+			Self::deposit_event(RawEvent::BondWithdrawn(who, server.bond));
+			Ok(())
+		}
+
+		/// Report misbehaviour of the key server. Only key servers from the set could report their peers.
+		/// Once `MisbehaviourReportThreshold` distinct peers have reported the offender, its bond is
+		/// slashed and it is removed from the set.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::REPORT_MISBEHAVIOUR)]
+		pub fn report_misbehaviour(origin, offender: NodeId, evidence: Misbehaviour) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let reporter = Self::bonded_key_server(&who).ok_or(Error::NotKeyServer)?;
+			ensure!(reporter.node_id != offender, Error::SelfReport);
+			let offender_account = Self::key_server_owner(&offender).ok_or(Error::UnknownOffender)?;
+			ensure!(evidence.is_signed_by(&offender), Error::InvalidEvidence);
+			ensure!(T::VerifyMisbehaviour::is_invalid(&evidence.kind, &evidence.message), Error::InvalidEvidence);
+
+			let mut reports = Self::misbehaviour_reports(&offender);
+			ensure!(!reports.contains(&who), Error::DuplicateReport);
+			reports.push(who.clone());
+
+			Self::deposit_event(RawEvent::MisbehaviourReported(who, offender.clone(), evidence.kind));
+
+			if reports.len() as u32 >= Self::misbehaviour_report_threshold() {
+				Self::slash_key_server(&offender_account, &offender);
+			} else {
+				<MisbehaviourReports<T>>::insert(&offender, reports);
+			}
+
+			Ok(())
+		}
+
+		/// Submit the key server heartbeat. Must be sent by the key server owner and signed with the node key.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::HEARTBEAT)]
+		pub fn heartbeat(origin, heartbeat: Heartbeat<T::BlockNumber>, signature: Vec<u8>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(Self::key_server_owner(&heartbeat.node_id) == Some(who.clone()), Error::NotKeyServerOwner);
+			ensure!(<Bonds<T>>::exists(&who), Error::NotKeyServer);
+			ensure!(is_signed_by_key(&heartbeat.node_id, &signature, &heartbeat.hash()), Error::InvalidHeartbeatSignature);
+			ensure!(heartbeat.block_number <= <system::Module<T>>::block_number(), Error::FutureHeartbeat);
+			ensure!(
+				Self::last_seen(&heartbeat.node_id).map(|last_seen| heartbeat.block_number > last_seen).unwrap_or(true),
+				Error::OutdatedHeartbeat
+			);
+
+			<LastSeen<T>>::insert(&heartbeat.node_id, heartbeat.block_number);
+			Self::schedule_heartbeat_deadline(&heartbeat.node_id, heartbeat.block_number);
+			if Self::offline_key_servers().contains(&heartbeat.node_id) {
+				OfflineKeyServers::mutate(|servers| servers.retain(|server| server != &heartbeat.node_id));
+				Self::deposit_event(RawEvent::KeyServerOnline(heartbeat.node_id));
+			}
+
+			Ok(())
+		}
+
+		fn on_initialize(now: T::BlockNumber) {
+			if Self::storage_version() < migration::CURRENT_STORAGE_VERSION && !Self::migration_failed() {
+				migration::on_runtime_upgrade::<T>();
+			}
+			Self::open_time_locks(now);
+			Self::renew_subscriptions(now);
+			let checked_key_servers = Self::mark_offline_key_servers(now);
+			<system::Module<T>>::register_extra_weight_unchecked(weights::HEARTBEAT_DEADLINES.weigh(checked_key_servers));
+		}
+
+		fn on_finalize(now: T::BlockNumber) {
+			Self::expire_access_requests(now);
+			Self::expire_proposals(now);
+			Self::expire_requests(now);
+		}
+
+		/// Store the encrypted document key. The sender becomes the document owner.
+		#[weight = WeightOf(
+			|(_, key): (&u64, &EncryptedDocumentKey)|
+				weights::with_permissions(weights::STORE_DOCUMENT_KEY.weigh(key.len()), <Module<T>>::permissions_size())
+		)]
+		pub fn store_document_key(origin, document_id: u64, key: EncryptedDocumentKey) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(!Self::permissions().has_document(document_id), Error::DocumentKeyAlreadyStored);
+
+			Self::mutate_permissions(|permissions| permissions.add_document_key_pair(&who, document_id, key));
+
+			Self::deposit_event(RawEvent::DocumentKeyStored(who, document_id));
+			Ok(())
+		}
+
+		/// Store the encrypted document key, owned by several accounts. Grants, revocations and deletion
+		/// of the document must then be approved by at least `threshold` owners.
+		#[weight = WeightOf(
+			|(_, key, owners, _): (&u64, &EncryptedDocumentKey, &Vec<T::AccountId>, &u32)|
+				weights::with_permissions(
+					weights::STORE_CO_OWNED_DOCUMENT_KEY.weigh(owners.len().saturating_mul(owners.len()))
+						.saturating_add(weights::STORE_DOCUMENT_KEY.weigh(key.len())),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn store_co_owned_document_key(
+			origin,
+			document_id: u64,
+			key: EncryptedDocumentKey,
+			owners: Vec<T::AccountId>,
+			threshold: u32
+		) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(!Self::permissions().has_document(document_id), Error::DocumentKeyAlreadyStored);
+			ensure!(owners.contains(&who), Error::SenderNotCoOwner);
+			ensure!(
+				owners.iter().enumerate().all(|(index, owner)| !owners[..index].contains(owner)),
+				Error::DuplicateOwner
+			);
+			ensure!(threshold != 0 && threshold as usize <= owners.len(), Error::InvalidApprovalThreshold);
+
+			Self::mutate_permissions(|permissions| permissions.add_co_owned_document_key_pair(owners, threshold, document_id, key));
+
+			Self::deposit_event(RawEvent::DocumentKeyStored(who, document_id));
+			Ok(())
+		}
+
+		/// Allow the account to retrieve the document key. Only the document owner could grant access.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::PERMISSIONS_CHANGE, <Module<T>>::permissions_size)]
+		pub fn grant_access(origin, document_id: u64, account: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+
+			Self::execute_document_action(document_id, DocumentAction::Grant(account))
+		}
+
+		/// Disallow the account to retrieve the document key. Only the document owner could revoke access.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::PERMISSIONS_CHANGE, <Module<T>>::permissions_size)]
+		pub fn revoke_access(origin, document_id: u64, account: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+
+			Self::execute_document_action(document_id, DocumentAction::Revoke(account))
+		}
+
+		/// Allow every account to retrieve every document key, approving their pending access requests.
+		/// Either all permissions are granted, or none of them. Only the owner of all documents could grant access.
+		#[weight = WeightOf(
+			|(document_ids, accounts): (&Vec<u64>, &Vec<T::AccountId>)|
+				weights::with_permissions(
+					weights::BATCH_PERMISSIONS_CHANGE.weigh(document_ids.len().saturating_mul(accounts.len())),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn batch_grant(origin, document_ids: Vec<u64>, accounts: Vec<T::AccountId>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_batch_control(&who, &document_ids)?;
+
+			Self::mutate_permissions(|permissions| permissions.set_addresses_allowed(&accounts, &document_ids));
+			for document_id in document_ids {
+				for account in &accounts {
+					if Self::take_access_request(document_id, account).is_ok() {
+						Self::deposit_event(RawEvent::AccessRequestApproved(document_id, account.clone()));
+					}
+					Self::deposit_event(RawEvent::AccessGranted(document_id, account.clone()));
+				}
+			}
+			Ok(())
+		}
+
+		/// Disallow every account to retrieve every document key. Either all permissions are revoked,
+		/// or none of them. Only the owner of all documents could revoke access.
+		#[weight = WeightOf(
+			|(document_ids, accounts): (&Vec<u64>, &Vec<T::AccountId>)|
+				weights::with_permissions(
+					weights::BATCH_PERMISSIONS_CHANGE.weigh(document_ids.len().saturating_mul(accounts.len())),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn batch_revoke(origin, document_ids: Vec<u64>, accounts: Vec<T::AccountId>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_batch_control(&who, &document_ids)?;
+
+			Self::mutate_permissions(|permissions| permissions.set_addresses_disallowed(&accounts, &document_ids));
+			for document_id in document_ids {
+				for account in &accounts {
+					Self::refund_purchase(document_id, account);
+					Self::deposit_event(RawEvent::AccessRevoked(document_id, account.clone()));
+				}
+			}
+			Ok(())
+		}
+
+		/// Delete the document key along with all permissions to retrieve it.
+		#[weight = WeightOf(
+			|(document_id,): (&u64,)|
+				weights::with_permissions(
+					weights::DOCUMENT_REMOVAL.weigh(<Module<T>>::open_proposals(document_id) as usize),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn delete_document(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+
+			Self::execute_document_action(document_id, DocumentAction::Delete)
+		}
+
+		/// Time lock the document key: until `release_at` block only the owners could retrieve it, and then
+		/// it becomes retrievable by `beneficiaries` (or by everyone, if None). The time lock can't be changed.
+		#[weight = WeightOf(
+			|(_, _, beneficiaries): (&u64, &T::BlockNumber, &Option<Vec<T::AccountId>>)|
+				weights::with_permissions(
+					weights::DOCUMENT_ACCOUNTS.weigh(beneficiaries.as_ref().map_or(0, Vec::len)),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn set_release_schedule(
+			origin,
+			document_id: u64,
+			release_at: T::BlockNumber,
+			beneficiaries: Option<Vec<T::AccountId>>
+		) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			ensure!(!<ReleaseSchedules<T>>::exists(document_id), Error::AlreadyTimeLocked);
+			ensure!(release_at > <system::Module<T>>::block_number(), Error::ReleaseInPast);
+
+			<ReleaseSchedules<T>>::insert(document_id, ReleaseSchedule { release_at, beneficiaries });
+			<ScheduledReleases<T>>::mutate(release_at, |documents| documents.push(document_id));
+
+			Self::deposit_event(RawEvent::ReleaseScheduled(document_id, release_at));
+			Ok(())
+		}
+
+		/// Let the beneficiaries retrieve the document key if the sender fails to check in for
+		/// `inactivity_period` blocks. Replaces the previous dead-man's switch of the document.
+		#[weight = WeightOf(
+			|(_, beneficiaries, _): (&u64, &Vec<T::AccountId>, &T::BlockNumber)|
+				weights::with_permissions(weights::DOCUMENT_ACCOUNTS.weigh(beneficiaries.len()), <Module<T>>::permissions_size())
+		)]
+		pub fn set_inheritance(
+			origin,
+			document_id: u64,
+			beneficiaries: Vec<T::AccountId>,
+			inactivity_period: T::BlockNumber
+		) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			ensure!(!beneficiaries.is_empty(), Error::NoBeneficiaries);
+			ensure!(!inactivity_period.is_zero(), Error::ZeroInactivityPeriod);
+
+			<Inheritances<T>>::insert(document_id, Inheritance { owner: who.clone(), beneficiaries, inactivity_period });
+			Self::do_check_in(who.clone());
+
+			Self::deposit_event(RawEvent::InheritanceSet(document_id, who));
+			Ok(())
+		}
+
+		/// Remove the dead-man's switch of the document.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn remove_inheritance(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			ensure!(<Inheritances<T>>::exists(document_id), Error::NoInheritance);
+
+			<Inheritances<T>>::remove(document_id);
+
+			Self::deposit_event(RawEvent::InheritanceRemoved(document_id));
+			Ok(())
+		}
+
+		/// Confirm that the sender is still active, postponing release of its documents to the beneficiaries.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::DOCUMENT_OPERATION)]
+		pub fn check_in(origin) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_check_in(who);
+			Ok(())
+		}
+
+		/// Designate accounts, which could claim emergency access to the document. Pending claims of
+		/// the accounts, which are no longer designated, are dropped.
+		#[weight = WeightOf(
+			|(_, accounts): (&u64, &Vec<T::AccountId>)|
+				weights::with_permissions(weights::DOCUMENT_ACCOUNTS.weigh(accounts.len()), <Module<T>>::permissions_size())
+		)]
+		pub fn set_emergency_accounts(origin, document_id: u64, accounts: Vec<T::AccountId>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+
+			for account in Self::emergency_accounts(document_id) {
+				if !accounts.contains(&account) {
+					<EmergencyClaims<T>>::remove(&(document_id, account));
+				}
+			}
+			<EmergencyAccounts<T>>::insert(document_id, accounts);
+
+			Self::deposit_event(RawEvent::EmergencyAccountsSet(document_id));
+			Ok(())
+		}
+
+		/// Claim emergency access to the document. Access is granted once `EmergencyAccessDelay` blocks
+		/// have passed without the owner veto.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::DOCUMENT_OPERATION)]
+		pub fn claim_emergency_access(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(Self::emergency_accounts(document_id).contains(&who), Error::NotEmergencyAccount);
+			let key = (document_id, who.clone());
+			ensure!(!<EmergencyClaims<T>>::exists(&key), Error::EmergencyAccessAlreadyClaimed);
+
+			let claimable_at = <system::Module<T>>::block_number() + Self::emergency_access_delay();
+			<EmergencyClaims<T>>::insert(&key, claimable_at);
+			Self::record_audit(document_id, who.clone(), AuditKind::EmergencyAccessClaimed);
+
+			Self::deposit_event(RawEvent::EmergencyAccessClaimed(document_id, who, claimable_at));
+			Ok(())
+		}
+
+		/// Veto the pending emergency access claim. Could be done by any document owner.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn veto_emergency_access(origin, document_id: u64, claimant: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_document_owner(&who, document_id)?;
+			<EmergencyClaims<T>>::take(&(document_id, claimant.clone())).ok_or(Error::NoEmergencyClaim)?;
+
+			Self::record_audit(document_id, claimant.clone(), AuditKind::EmergencyAccessVetoed);
+
+			Self::deposit_event(RawEvent::EmergencyAccessVetoed(document_id, claimant));
+			Ok(())
+		}
+
+		/// Complete the emergency access claim, once the challenge delay has passed.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn complete_emergency_access(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(Self::emergency_accounts(document_id).contains(&who), Error::NotEmergencyAccount);
+			let key = (document_id, who.clone());
+			let claimable_at = Self::emergency_claim(&key).ok_or(Error::NoEmergencyClaim)?;
+			ensure!(<system::Module<T>>::block_number() >= claimable_at, Error::EmergencyAccessDelayNotPassed);
+
+			Self::execute_document_action(document_id, DocumentAction::Grant(who.clone()))?;
+			<EmergencyClaims<T>>::remove(&key);
+			Self::record_audit(document_id, who.clone(), AuditKind::EmergencyAccessGranted);
+
+			Self::deposit_event(RawEvent::EmergencyAccessGranted(document_id, who));
+			Ok(())
+		}
+
+		/// Replace the document key. Access, purchased but not yet retrieved, is revoked and refunded.
+		#[weight = WeightOf(
+			|(_, key): (&u64, &EncryptedDocumentKey)|
+				weights::with_permissions(weights::STORE_DOCUMENT_KEY.weigh(key.len()), <Module<T>>::permissions_size())
+		)]
+		pub fn rekey_document(origin, document_id: u64, key: EncryptedDocumentKey) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+
+			Self::mutate_permissions(|permissions| permissions.set_document_key(document_id, key));
+			for buyer in Self::refund_purchases(document_id) {
+				Self::mutate_permissions(|permissions| permissions.set_address_disallowed(&buyer, document_id));
+				Self::deposit_event(RawEvent::AccessRevoked(document_id, buyer));
+			}
+
+			Self::deposit_event(RawEvent::DocumentRekeyed(document_id));
+			Ok(())
+		}
+
+		/// Offer access to the document key for sale.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn list_document(origin, document_id: u64, price: BalanceOf<T>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+
+			<Listings<T>>::insert(document_id, Listing { seller: who, price });
+
+			Self::deposit_event(RawEvent::DocumentListed(document_id, price));
+			Ok(())
+		}
+
+		/// Withdraw the offer. Existing purchases are not affected.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn unlist_document(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			<Listings<T>>::take(document_id).ok_or(Error::NotListed)?;
+
+			Self::deposit_event(RawEvent::DocumentUnlisted(document_id));
+			Ok(())
+		}
+
+		/// Buy access to the document key. The payment is held in escrow and released to the seller
+		/// once the key is delivered to the buyer.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_PAYMENT, <Module<T>>::permissions_size)]
+		pub fn buy_access(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let listing = Self::listing(document_id).ok_or(Error::NotListed)?;
+			ensure!(!Self::has_permission(&who, document_id), Error::AccessAlreadyGranted);
+			ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+
+			// purchase of the expired access could never be settled, so it is replaced by the new one
+			Self::refund_purchase(document_id, &who);
+			T::Currency::reserve(&who, listing.price).map_err(|_| Error::InsufficientFunds)?;
+			<Purchases<T>>::insert((document_id, who.clone()), Purchase { seller: listing.seller, price: listing.price });
+			<DocumentBuyers<T>>::mutate(document_id, |buyers| buyers.push(who.clone()));
+			Self::mutate_permissions(|permissions| permissions.set_address_allowed(&who, document_id));
+
+			Self::deposit_event(RawEvent::AccessPurchased(document_id, who.clone(), listing.price));
+			Self::deposit_event(RawEvent::AccessGranted(document_id, who));
+			Ok(())
+		}
+
+		/// Confirm that the purchased key has been retrieved, releasing the payment to the seller.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::DOCUMENT_PAYMENT)]
+		pub fn confirm_purchase(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(Self::settle_purchase(document_id, &who), Error::NoPendingPurchase);
+			Ok(())
+		}
+
+		/// Cancel the purchase, which access couldn't be used right now (the document is time locked or
+		/// frozen), returning the payment and giving up the purchased access.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_PAYMENT, <Module<T>>::permissions_size)]
+		pub fn cancel_purchase(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(<Purchases<T>>::exists((document_id, who.clone())), Error::NoPendingPurchase);
+			ensure!(Self::check_access(&who, document_id).is_err(), Error::PurchaseUsable);
+
+			Self::refund_purchase(document_id, &who);
+			Self::mutate_permissions(|permissions| permissions.set_address_disallowed(&who, document_id));
+
+			Self::deposit_event(RawEvent::AccessRevoked(document_id, who));
+			Ok(())
+		}
+
+		/// Allow the account to retrieve the document key until the given block.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::PERMISSIONS_CHANGE, <Module<T>>::permissions_size)]
+		pub fn grant_temporary_access(origin, document_id: u64, account: T::AccountId, expires_at: T::BlockNumber) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+			ensure!(expires_at > <system::Module<T>>::block_number(), Error::AccessExpiryInPast);
+
+			Self::mutate_permissions(|permissions| permissions.set_address_allowed_until(&account, document_id, expires_at));
+
+			Self::deposit_event(RawEvent::TemporaryAccessGranted(document_id, account, expires_at));
+			Ok(())
+		}
+
+		/// Offer access to the document key by subscription, charging the price every period. Existing
+		/// subscriptions to the previous offer lapse at the end of their billing period.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn offer_subscription(origin, document_id: u64, price: BalanceOf<T>, period: T::BlockNumber) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			ensure!(!period.is_zero(), Error::ZeroBillingPeriod);
+
+			<SubscriptionPlans<T>>::insert(document_id, SubscriptionPlan { seller: who, price, period });
+
+			Self::deposit_event(RawEvent::SubscriptionOffered(document_id, price, period));
+			Ok(())
+		}
+
+		/// Withdraw the subscription offer. Existing subscriptions lapse at the end of their billing period.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn withdraw_subscription_offer(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_sole_control(&who, document_id)?;
+			<SubscriptionPlans<T>>::take(document_id).ok_or(Error::NoSubscriptionOffer)?;
+
+			Self::deposit_event(RawEvent::SubscriptionOfferWithdrawn(document_id));
+			Ok(())
+		}
+
+		/// Subscribe to the document key access, paying for the first billing period.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_PAYMENT, <Module<T>>::permissions_size)]
+		pub fn subscribe(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let plan = Self::subscription_plan(document_id).ok_or(Error::NoSubscriptionOffer)?;
+			let key = (document_id, who.clone());
+			ensure!(!<Subscriptions<T>>::exists(&key), Error::AlreadySubscribed);
+			ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+
+			T::Currency::transfer(&who, &plan.seller, plan.price).map_err(|_| Error::InsufficientFunds)?;
+			let renews_at = <system::Module<T>>::block_number() + plan.period;
+			Self::extend_subscription(document_id, who.clone(), plan, renews_at);
+
+			Self::deposit_event(RawEvent::Subscribed(document_id, who, renews_at));
+			Ok(())
+		}
+
+		/// Stop renewing the subscription. Access remains until the end of the paid billing period.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::DOCUMENT_OPERATION)]
+		pub fn unsubscribe(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let subscription = <Subscriptions<T>>::take((document_id, who.clone())).ok_or(Error::NotSubscribed)?;
+			<SubscriptionRenewals<T>>::mutate(subscription.renews_at, |renewals| renewals.retain(|renewal| renewal != &(document_id, who.clone())));
+
+			Self::deposit_event(RawEvent::Unsubscribed(document_id, who));
+			Ok(())
+		}
+
+		/// Propose the change of the co-owned document. The proposal is approved by the sender and
+		/// expires after `ProposalTtl` blocks.
+		#[weight = WeightOf(
+			|(document_id, _): (&u64, &DocumentAction<T::AccountId>)|
+				weights::with_permissions(
+					weights::DOCUMENT_REMOVAL.weigh(<Module<T>>::open_proposals(document_id) as usize),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn propose_document_action(origin, document_id: u64, action: DocumentAction<T::AccountId>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_document_owner(&who, document_id)?;
+			ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+
+			Self::do_propose_document_action(who, document_id, action)
+		}
+
+		/// Approve the proposed change of the co-owned document. The change is executed once
+		/// enough owners have approved it.
+		#[weight = WeightOf(
+			|(document_id, _): (&u64, &u32)|
+				weights::with_permissions(
+					weights::DOCUMENT_REMOVAL.weigh(<Module<T>>::open_proposals(document_id) as usize),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn approve_proposal(origin, document_id: u64, index: u32) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_document_owner(&who, document_id)?;
+
+			Self::approve_document_action(who, document_id, index)
+		}
+
+		/// Request the document owner to allow the sender to retrieve the document key.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn request_access(origin, document_id: u64, message_hash: Option<MessageHash>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(Self::permissions().has_document(document_id), Error::DocumentKeyIsNotFound);
+			ensure!(!Self::has_permission(&who, document_id), Error::AccessAlreadyGranted);
+			let key = (document_id, who.clone());
+			ensure!(!<AccessRequests<T>>::exists(&key), Error::AccessAlreadyRequested);
+
+			let expires_at = <system::Module<T>>::block_number() + Self::access_request_ttl();
+			<AccessRequests<T>>::insert(&key, AccessRequest { message_hash: message_hash.clone(), expires_at, proposal: None });
+			<AccessRequestExpiries<T>>::mutate(expires_at, |requests| requests.push(key));
+
+			Self::deposit_event(RawEvent::AccessRequested(who, document_id, message_hash));
+			Ok(())
+		}
+
+		/// Approve the pending access request. Only the document owner could approve requests. Approval
+		/// of the co-owned document request is proposed as the grant (once per request), and the request
+		/// is consumed once enough owners have approved the grant.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::PERMISSIONS_CHANGE, <Module<T>>::permissions_size)]
+		pub fn approve_access_request(origin, document_id: u64, requester: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_document_owner(&who, document_id)?;
+			ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+			let key = (document_id, requester.clone());
+			let request = Self::access_request(&key).ok_or(Error::NoAccessRequest)?;
+
+			let action = DocumentAction::Grant(requester);
+			if Self::permissions().document_approval_threshold(document_id) == Some(1) {
+				return Self::execute_document_action(document_id, action);
+			}
+
+			match request.proposal.filter(|index| <Proposals<T>>::exists(&document_id, index)) {
+				Some(index) => Self::approve_document_action(who, document_id, index),
+				None => {
+					let proposal = Some(Self::proposal_count(document_id));
+					<AccessRequests<T>>::insert(&key, AccessRequest { proposal, ..request });
+					Self::do_propose_document_action(who, document_id, action)
+				},
+			}
+		}
+
+		/// Reject the pending access request. Only the document owner could reject requests.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_OPERATION, <Module<T>>::permissions_size)]
+		pub fn reject_access_request(origin, document_id: u64, requester: T::AccountId) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_document_owner(&who, document_id)?;
+			Self::take_access_request(document_id, &requester)?;
+
+			Self::deposit_event(RawEvent::AccessRequestRejected(document_id, requester));
+			Ok(())
+		}
+
+		/// Publish the public key, so that it could be found by its address. Only the owner of the key could
+		/// publish it: the proof is the signature of `encryption_key_proof_hash(sender)`, made with the key.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::PUBLISH_PUBLIC)]
+		pub fn publish_public(origin, public: Public, proof: Vec<u8>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_encryption_key_proof(&who, &public, &proof)?;
+			let address = crypto::public_to_address(&public);
+			ensure!(!AddressPublics::exists(&address), Error::PublicAlreadyPublished);
+
+			AddressPublics::insert(&address, public);
+
+			Self::deposit_event(RawEvent::PublicPublished(address));
+			Ok(())
+		}
+
+		/// Add the key, used to encrypt data for the sender. The proof is the signature of
+		/// `encryption_key_proof_hash(sender)`, made with the key.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::ENCRYPTION_KEY_MANAGEMENT)]
+		pub fn add_encryption_key(origin, public: Public, proof: Vec<u8>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_encryption_key_proof(&who, &public, &proof)?;
+
+			let mut keys = Self::encryption_keys(&who);
+			ensure!(!keys.contains(&public), Error::EncryptionKeyAlreadyAdded);
+			ensure!((keys.len() as u32) < Self::max_encryption_keys(), Error::TooManyEncryptionKeys);
+			keys.push(public.clone());
+			<EncryptionKeys<T>>::insert(&who, keys);
+			Self::publish_encryption_key(&public);
+
+			Self::deposit_event(RawEvent::EncryptionKeyAdded(who, public));
+			Ok(())
+		}
+
+		/// Remove the encryption key of the sender.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::DOCUMENT_OPERATION)]
+		pub fn remove_encryption_key(origin, public: Public) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let mut keys = Self::encryption_keys(&who);
+			let index = keys.iter().position(|key| *key == public).ok_or(Error::EncryptionKeyNotFound)?;
+			keys.remove(index);
+			<EncryptionKeys<T>>::insert(&who, keys);
+
+			Self::deposit_event(RawEvent::EncryptionKeyRemoved(who, public));
+			Ok(())
+		}
+
+		/// Replace the encryption key of the sender with the new key. The proof is the signature of
+		/// `encryption_key_proof_hash(sender)`, made with the new key.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::ENCRYPTION_KEY_MANAGEMENT)]
+		pub fn rotate_encryption_key(origin, old_public: Public, new_public: Public, proof: Vec<u8>) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::ensure_encryption_key_proof(&who, &new_public, &proof)?;
+
+			let mut keys = Self::encryption_keys(&who);
+			ensure!(!keys.contains(&new_public), Error::EncryptionKeyAlreadyAdded);
+			let index = keys.iter().position(|key| *key == old_public).ok_or(Error::EncryptionKeyNotFound)?;
+			keys[index] = new_public.clone();
+			<EncryptionKeys<T>>::insert(&who, keys);
+			Self::publish_encryption_key(&new_public);
+
+			Self::deposit_event(RawEvent::EncryptionKeyRotated(who, old_public, new_public));
+			Ok(())
+		}
+
+		/// Retrieve the encrypted document key, recording the access in the audit trail.
+		/// The key is delivered in the `DocumentKeyRetrieved` event, settling the pending purchase.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::DOCUMENT_PAYMENT, <Module<T>>::permissions_size)]
+		pub fn retrieve_document_key(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let key = Self::get_encrypted_document_key(&who, document_id).map_err(Error::from)?;
+
+			Self::record_audit(document_id, who.clone(), AuditKind::DocumentKeyRetrieved);
+			Self::settle_purchase(document_id, &who);
+
+			Self::deposit_event(RawEvent::DocumentKeyRetrieved(who, document_id, key));
+			Ok(())
+		}
+
+		/// Request the key servers to generate server key for the document.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::KEY_SERVER_REQUEST, <Module<T>>::permissions_size)]
+		pub fn generate_server_key(origin, document_id: u64, threshold: u8) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(!<ServerKeys>::exists(document_id), Error::ServerKeyAlreadyGenerated);
+			ensure!(Self::permissions().has_document(document_id), Error::DocumentKeyIsNotFound);
+			ensure!(Self::permissions().is_document_owner(&who, document_id), Error::NotDocumentOwner);
+
+			Self::file_request(&who, PendingRequest::ServerKeyGeneration { document_id, author: who.clone(), threshold })
+		}
+
+		/// Request the key servers to retrieve server key of the document.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::KEY_SERVER_REQUEST, <Module<T>>::permissions_size)]
+		pub fn retrieve_server_key(origin, document_id: u64) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			ensure!(!<ServerKeys>::exists(document_id), Error::ServerKeyAlreadyGenerated);
+			ensure!(Self::permissions().has_document(document_id), Error::DocumentKeyIsNotFound);
+
+			Self::file_request(&who, PendingRequest::ServerKeyRetrieval { document_id })
+		}
+
+		/// Request the key servers to retrieve document key shadow, encrypted with the requester public key.
+		#[weight = WithPermissions(DispatchClass::Normal, weights::KEY_SERVER_REQUEST, <Module<T>>::permissions_size)]
+		pub fn retrieve_document_key_shadow(origin, document_id: u64, requester_public: Public) -> Result {
+			let who = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::check_access(&who, document_id).map_err(Error::from)?;
+
+			Self::file_request(&who, PendingRequest::DocumentKeyShadowRetrieval {
+				document_id,
+				requester: who.clone(),
+				requester_public,
+			})?;
+			Self::record_audit(document_id, who.clone(), AuditKind::DocumentKeyShadowRequested);
+			Ok(())
+		}
+
+		/// Key server response: server key has been generated. The key is stored once the majority of
+		/// online key servers has submitted it. Stored server key is never replaced.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::KEY_SERVER_RESPONSE.base)]
+		pub fn server_key_generated(origin, document_id: u64, public: Public) -> Result {
+			let node_id = Self::ensure_key_server(origin)?;
+			Self::ensure_storage_migrated()?;
+			let response = Call::<T>::server_key_generated(document_id, public.clone());
+			let request = Self::accept_response(node_id, |request| match *request {
+				PendingRequest::ServerKeyGeneration { document_id: id, .. } => id == document_id,
+				_ => false,
+			}, &response)?;
+
+			if let Some(PendingRequest::ServerKeyGeneration { threshold, .. }) = request {
+				if Self::store_server_key(document_id, ServerKey { public: public.clone(), threshold }) {
+					Self::deposit_event(RawEvent::ServerKeyGenerated(document_id, public));
+				}
+			}
+			Ok(())
+		}
+
+		/// Key server response: server key has been retrieved. The key is stored once the majority of
+		/// online key servers has submitted it. Stored server key is never replaced.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::KEY_SERVER_RESPONSE.base)]
+		pub fn server_key_retrieved(origin, document_id: u64, public: Public, threshold: u8) -> Result {
+			let node_id = Self::ensure_key_server(origin)?;
+			Self::ensure_storage_migrated()?;
+			let response = Call::<T>::server_key_retrieved(document_id, public.clone(), threshold);
+			let request = Self::accept_response(
+				node_id,
+				|request| *request == PendingRequest::ServerKeyRetrieval { document_id },
+				&response,
+			)?;
+
+			if request.is_some() && Self::store_server_key(document_id, ServerKey { public: public.clone(), threshold }) {
+				Self::deposit_event(RawEvent::ServerKeyRetrieved(document_id, public));
+			}
+			Ok(())
+		}
+
+		/// Key server response: document key shadow has been retrieved. The shadow is released once the majority
+		/// of online key servers has submitted it, settling the pending purchase. Request of the requester, which
+		/// has lost access, fails.
+		#[weight = WeightOf(
+			|(_, _, _, _, decrypt_shadows): (&u64, &T::AccountId, &Public, &Public, &Vec<Vec<u8>>)|
+				weights::with_permissions(weights::KEY_SERVER_RESPONSE.weigh(decrypt_shadows.len()), <Module<T>>::permissions_size())
+		)]
+		pub fn document_key_shadow_retrieved(
+			origin,
+			document_id: u64,
+			requester: T::AccountId,
+			decrypted_secret: Public,
+			common_point: Public,
+			decrypt_shadows: Vec<Vec<u8>>
+		) -> Result {
+			let node_id = Self::ensure_key_server(origin)?;
+			Self::ensure_storage_migrated()?;
+			match Self::check_access(&requester, document_id) {
+				Ok(()) => (),
+				Err(types::Error::KeyReleaseFrozen) => return Err(Error::KeyReleaseFrozen),
+				Err(_) => {
+					let request = Self::take_request(|request| Self::is_shadow_retrieval_request(request, document_id, &requester))?;
+					Self::deposit_event(RawEvent::RequestFailed(request.document_id()));
+					return Ok(());
+				},
+			}
+			let response = Call::<T>::document_key_shadow_retrieved(
+				document_id,
+				requester.clone(),
+				decrypted_secret.clone(),
+				common_point.clone(),
+				decrypt_shadows.clone(),
+			);
+			let request = Self::accept_response(
+				node_id,
+				|request| Self::is_shadow_retrieval_request(request, document_id, &requester),
+				&response,
+			)?;
+			if request.is_none() {
+				return Ok(());
+			}
+
+			Self::record_audit(document_id, requester.clone(), AuditKind::DocumentKeyShadowReleased);
+			Self::settle_purchase(document_id, &requester);
+			Self::deposit_event(RawEvent::DocumentKeyShadowRetrieved(
+				requester,
+				document_id,
+				decrypted_secret,
+				common_point,
+				decrypt_shadows,
+			));
+			Ok(())
+		}
+
+		/// Key server response: the request has failed. The request is dropped once the majority of
+		/// online key servers has reported the failure.
+		#[weight = SimpleDispatchInfo::FixedNormal(weights::KEY_SERVER_RESPONSE.base)]
+		pub fn request_failed(origin, request: PendingRequest<T::AccountId>) -> Result {
+			let node_id = Self::ensure_key_server(origin)?;
+			Self::ensure_storage_migrated()?;
+			let response = Call::<T>::request_failed(request.clone());
+			if let Some(request) = Self::accept_response(node_id, |pending| pending == &request, &response)? {
+				Self::deposit_event(RawEvent::RequestFailed(request.document_id()));
+			}
+			Ok(())
+		}
+
+		fn offchain_worker(_now: T::BlockNumber) {
+			Self::submit_pending_responses();
+		}
+
+		/// Disallow the account to retrieve the document key, bypassing the owner.
+		#[weight = WithPermissions(DispatchClass::Operational, weights::ADMIN_OPERATION, <Module<T>>::permissions_size)]
+		pub fn force_revoke_access(origin, document_id: u64, account: T::AccountId) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_force_revoke_access(document_id, account)
+		}
+
+		/// Delete the document key along with all permissions to retrieve it, bypassing the owner.
+		#[weight = ClassifiedWeightOf(
+			DispatchClass::Operational,
+			|(document_id,): (&u64,)|
+				weights::with_permissions(
+					weights::DOCUMENT_REMOVAL.weigh(<Module<T>>::open_proposals(document_id) as usize),
+					<Module<T>>::permissions_size(),
+				)
+		)]
+		pub fn force_delete_document(origin, document_id: u64) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_force_delete_document(document_id)
+		}
+
+		/// Freeze the document: its key could not be retrieved and its permissions could not be changed by the owner.
+		#[weight = WithPermissions(DispatchClass::Operational, weights::ADMIN_OPERATION, <Module<T>>::permissions_size)]
+		pub fn freeze_document(origin, document_id: u64) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_freeze_document(document_id)
+		}
+
+		/// Unfreeze previously frozen document.
+		#[weight = SimpleDispatchInfo::FixedOperational(weights::ADMIN_OPERATION)]
+		pub fn unfreeze_document(origin, document_id: u64) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_unfreeze_document(document_id)
+		}
+
+		/// Update parameters of the key servers set. Parameters that are None are left unchanged.
+		/// The new bond only applies to key servers registered after the update.
+		#[weight = SimpleDispatchInfo::FixedOperational(weights::ADMIN_OPERATION)]
+		pub fn set_server_set_parameters(
+			origin,
+			key_server_bond: Option<BalanceOf<T>>,
+			misbehaviour_report_threshold: Option<u32>,
+			heartbeat_timeout: Option<T::BlockNumber>
+		) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_set_server_set_parameters(key_server_bond, misbehaviour_report_threshold, heartbeat_timeout)
+		}
+
+		/// Halt release of all document keys and shadows.
+		#[weight = SimpleDispatchInfo::FixedOperational(weights::ADMIN_OPERATION)]
+		pub fn freeze_key_releases(origin) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_freeze_key_releases()
+		}
+
+		/// Resume release of document keys and shadows.
+		#[weight = SimpleDispatchInfo::FixedOperational(weights::ADMIN_OPERATION)]
+		pub fn unfreeze_key_releases(origin) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_unfreeze_key_releases()
+		}
+
+		/// Set the M-of-N administrators policy. None disables multisig administration.
+		#[weight = SimpleDispatchInfo::FixedOperational(weights::ADMIN_OPERATION)]
+		pub fn set_admin_policy(origin, policy: Option<AdminPolicy>) -> Result {
+			T::AdminOrigin::ensure_origin(origin)?;
+			Self::ensure_storage_migrated()?;
+			Self::do_set_admin_policy(policy)
+		}
+
+		/// Execute the admin operation, authorized by signatures of the administrators policy members.
+		/// Signatures must cover `admin_call_hash(admin_nonce, call)`, which is bound to this chain.
+		#[weight = WeightOf(
+			|(call, signatures): (&Box<Call<T>>, &Vec<Vec<u8>>)|
+				weights::ADMIN_CALL.weigh(signatures.len()).saturating_add(call.get_dispatch_info().weight)
+		)]
+		pub fn execute_admin_call(origin, call: Box<Call<T>>, signatures: Vec<Vec<u8>>) -> Result {
+			let _ = ensure_signed(origin)?;
+			Self::ensure_storage_migrated()?;
+			let policy = Self::admin_policy().ok_or(Error::NoAdminPolicy)?;
+
+			let nonce = Self::admin_nonce();
+			let signatures: Vec<_> = signatures.iter().filter_map(|signature| parse_signature(signature)).collect();
+			policy.verify(&Self::admin_call_hash(nonce, &call), &signatures)
+				.map_err(|_| Error::NotEnoughAdminSignatures)?;
+
+			Self::dispatch_admin_call(*call)?;
+			AdminNonce::put(nonce + 1);
+
+			Self::deposit_event(RawEvent::AdminCallExecuted(nonce));
+			Ok(())
+		}
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Hash of the admin call, which must be signed by the administrators. Includes the genesis hash,
+	/// so that signatures can't be replayed on other chains with the same administrators.
+	pub fn admin_call_hash(nonce: u64, call: &Call<T>) -> MessageHash {
+		let genesis_hash = <system::Module<T>>::block_hash(T::BlockNumber::zero());
+		runtime_io::blake2_256(&(&b"secret store admin call"[..], genesis_hash, nonce, call).encode()).into()
+	}
+
+	/// Dispatch the admin operation, which has already been authorized.
+	fn dispatch_admin_call(call: Call<T>) -> Result {
+		match call {
+			Call::force_revoke_access(document_id, account) => Self::do_force_revoke_access(document_id, account),
+			Call::force_delete_document(document_id) => Self::do_force_delete_document(document_id),
+			Call::freeze_document(document_id) => Self::do_freeze_document(document_id),
+			Call::unfreeze_document(document_id) => Self::do_unfreeze_document(document_id),
+			Call::set_server_set_parameters(key_server_bond, misbehaviour_report_threshold, heartbeat_timeout) =>
+				Self::do_set_server_set_parameters(key_server_bond, misbehaviour_report_threshold, heartbeat_timeout),
+			Call::set_admin_policy(policy) => Self::do_set_admin_policy(policy),
+			Call::freeze_key_releases() => Self::do_freeze_key_releases(),
+			Call::unfreeze_key_releases() => Self::do_unfreeze_key_releases(),
+			_ => Err(Error::NotAdminCall),
+		}
+	}
+
+	fn do_force_revoke_access(document_id: u64, account: T::AccountId) -> Result {
+		ensure!(Self::permissions().has_document(document_id), Error::DocumentKeyIsNotFound);
+
+		Self::mutate_permissions(|permissions| permissions.set_address_disallowed(&account, document_id));
+		Self::refund_purchase(document_id, &account);
+
+		Self::deposit_event(RawEvent::AccessRevoked(document_id, account));
+		Ok(())
+	}
+
+	fn do_force_delete_document(document_id: u64) -> Result {
+		ensure!(Self::permissions().has_document(document_id), Error::DocumentKeyIsNotFound);
+
+		Self::remove_document(document_id);
+		<FrozenDocuments>::remove(document_id);
+		Ok(())
+	}
+
+	fn do_freeze_document(document_id: u64) -> Result {
+		ensure!(Self::permissions().has_document(document_id), Error::DocumentKeyIsNotFound);
+		ensure!(!Self::is_document_frozen(document_id), Error::DocumentAlreadyFrozen);
+
+		<FrozenDocuments>::insert(document_id, true);
+
+		Self::deposit_event(RawEvent::DocumentFrozen(document_id));
+		Ok(())
+	}
+
+	fn do_unfreeze_document(document_id: u64) -> Result {
+		ensure!(Self::is_document_frozen(document_id), Error::DocumentNotFrozen);
+
+		<FrozenDocuments>::remove(document_id);
+
+		Self::deposit_event(RawEvent::DocumentUnfrozen(document_id));
+		Ok(())
+	}
+
+	fn do_freeze_key_releases() -> Result {
+		ensure!(!Self::key_releases_frozen(), Error::KeyReleasesAlreadyFrozen);
+
+		KeyReleasesFrozen::put(true);
+
+		Self::deposit_event(RawEvent::KeyReleasesFrozen);
+		Ok(())
+	}
+
+	fn do_unfreeze_key_releases() -> Result {
+		ensure!(Self::key_releases_frozen(), Error::KeyReleasesNotFrozen);
+
+		KeyReleasesFrozen::put(false);
+
+		Self::deposit_event(RawEvent::KeyReleasesUnfrozen);
+		Ok(())
+	}
+
+	fn do_set_server_set_parameters(
+		key_server_bond: Option<BalanceOf<T>>,
+		misbehaviour_report_threshold: Option<u32>,
+		heartbeat_timeout: Option<T::BlockNumber>,
+	) -> Result {
+		ensure!(misbehaviour_report_threshold != Some(0), Error::ZeroReportThreshold);
+
+		if let Some(key_server_bond) = key_server_bond {
+			<KeyServerBond<T>>::put(key_server_bond);
+		}
+		if let Some(misbehaviour_report_threshold) = misbehaviour_report_threshold {
+			MisbehaviourReportThreshold::put(misbehaviour_report_threshold);
+		}
+		if let Some(heartbeat_timeout) = heartbeat_timeout {
+			<HeartbeatTimeout<T>>::put(heartbeat_timeout);
+		}
+
+		Self::deposit_event(RawEvent::ServerSetParametersUpdated);
+		Ok(())
+	}
+
+	fn do_set_admin_policy(policy: Option<AdminPolicy>) -> Result {
+		if let Some(ref policy) = policy {
+			policy.validate().map_err(|_| Error::InvalidAdminPolicy)?;
+		}
+
+		Administrators::put(policy);
+
+		Self::deposit_event(RawEvent::AdminPolicyChanged);
+		Ok(())
+	}
+}
+
+impl<T: Trait> Module<T> {
+	/// Slash the whole bond of the key server, which is either in the set or unbonding, and remove it from the set.
+	fn slash_key_server(owner: &T::AccountId, node_id: &NodeId) {
+		let bond = match (<Bonds<T>>::take(owner), <Unbonding<T>>::take(owner)) {
+			(Some(server), _) => server.bond,
+			(None, Some(server)) => server.bond,
+			(None, None) => return,
+		};
+
+		let (imbalance, _) = T::Currency::slash_reserved(owner, bond);
+		T::Slash::on_unbalanced(imbalance);
+		Self::remove_key_server(node_id);
+		Self::forget_key_server(node_id);
+
+		Self::deposit_event(RawEvent::KeyServerSlashed(owner.clone(), node_id.clone(), bond));
+	}
+
+	/// Forget the owner and the reports of the key server, which has no bond left.
+	fn forget_key_server(node_id: &NodeId) {
+		<KeyServerOwners<T>>::remove(node_id);
+		<MisbehaviourReports<T>>::remove(node_id);
+	}
+
+	/// Remove the key server from the set. Its owner is remembered while the bond could be slashed.
+	fn remove_key_server(node_id: &NodeId) {
+		<LastSeen<T>>::remove(node_id);
+		KeyServers::mutate(|servers| servers.retain(|server| server != node_id));
+		OfflineKeyServers::mutate(|servers| servers.retain(|server| server != node_id));
+	}
+
+	/// Check the key server liveness at the first block after its heartbeat deadline. Decreased
+	/// `HeartbeatTimeout` only applies from the next heartbeat of the key server.
+	fn schedule_heartbeat_deadline(node_id: &NodeId, last_seen: T::BlockNumber) {
+		let next_block = <system::Module<T>>::block_number() + One::one();
+		let deadline = rstd::cmp::max(last_seen + Self::heartbeat_timeout() + One::one(), next_block);
+		<HeartbeatDeadlines<T>>::mutate(deadline, |servers| if !servers.contains(node_id) {
+			servers.push(node_id.clone());
+		});
+	}
+
+	/// Mark key servers, which heartbeat deadline has passed at the block, as offline. Servers, which have
+	/// sent heartbeats since the deadline has been scheduled, are skipped. Returns the number of checked servers.
+	fn mark_offline_key_servers(now: T::BlockNumber) -> usize {
+		let timeout = Self::heartbeat_timeout();
+		let deadlines = <HeartbeatDeadlines<T>>::take(now);
+		let mut offline = Self::offline_key_servers();
+		for node_id in &deadlines {
+			let last_seen = match Self::last_seen(node_id) {
+				Some(last_seen) => last_seen,
+				None => continue,
+			};
+			if now <= last_seen + timeout {
+				Self::schedule_heartbeat_deadline(node_id, last_seen);
+			} else if !offline.contains(node_id) {
+				offline.push(node_id.clone());
+				Self::deposit_event(RawEvent::KeyServerOffline(node_id.clone()));
+			}
+		}
+		if !deadlines.is_empty() {
+			OfflineKeyServers::put(offline);
+		}
+		deadlines.len()
+	}
+
+	/// Is the key server in the set and alive?
+	pub fn is_key_server_online(node_id: &NodeId) -> bool {
+		Self::key_servers().contains(node_id) && !Self::offline_key_servers().contains(node_id)
+	}
+
+	/// Node ids of the key servers that have recently submitted their heartbeats.
+	pub fn online_key_servers() -> Vec<NodeId> {
+		let offline = Self::offline_key_servers();
+		Self::key_servers().into_iter().filter(|server| !offline.contains(server)).collect()
+	}
+
+	/// Server key id, which is used by the key servers to identify the document.
+	pub fn server_key_id(document_id: u64) -> ServerKeyId {
+		ServerKeyId::from_low_u64_be(document_id)
+	}
+
+	/// Hash, which must be signed with the encryption key to prove its possession by the account.
+	pub fn encryption_key_proof_hash(who: &T::AccountId) -> MessageHash {
+		runtime_io::blake2_256(&(&b"secret store encryption key"[..], who).encode()).into()
+	}
+
+	/// Ensure that the proof of the encryption key possession is valid.
+	fn ensure_encryption_key_proof(who: &T::AccountId, public: &Public, proof: &[u8]) -> Result {
+		ensure!(is_signed_by_key(public, proof, &Self::encryption_key_proof_hash(who)), Error::InvalidEncryptionKeyProof);
+		Ok(())
+	}
+
+	/// Make the encryption key discoverable by its address.
+	fn publish_encryption_key(public: &Public) {
+		let address = crypto::public_to_address(public);
+		if !AddressPublics::exists(&address) {
+			AddressPublics::insert(&address, public);
+			Self::deposit_event(RawEvent::PublicPublished(address));
+		}
+	}
+
+	/// EIP-712 signing domain of the requests to this module. Chain id is the blake2-256 hash of the genesis
+	/// hash, verifying contract is the address, derived from the module name.
+	pub fn typed_data_domain() -> TypedDataDomain {
+		let genesis_hash = <system::Module<T>>::block_hash(T::BlockNumber::zero());
+		TypedDataDomain {
+			chain_id: runtime_io::blake2_256(&genesis_hash.encode()).into(),
+			verifying_contract: Address::from_slice(&runtime_io::keccak_256(b"secret store")[12..]),
+		}
+	}
+
+	/// Check that the typed data signature of the requester, if any, is made in the domain of this module.
+	fn ensure_typed_data_domain(requester: &Requester) -> result::Result<(), types::Error> {
+		match requester.typed_data_domain() {
+			Some(domain) if *domain != Self::typed_data_domain() =>
+				Err(types::Error::InsufficientRequesterData("request is signed in another domain".into())),
+			_ => Ok(()),
+		}
+	}
+
+	/// Public of the requester. Public of the address-only requester is looked up in the published publics.
+	pub fn requester_public(requester: &Requester, server_key_id: &ServerKeyId) -> result::Result<Public, types::Error> {
+		Self::ensure_typed_data_domain(requester)?;
+		requester.resolve_public(server_key_id, |address| Self::address_public(address))
+			.map_err(types::Error::InsufficientRequesterData)
+	}
+
+	/// Accept the signed request of the document by the key server replay cache at `now` (in seconds),
+	/// so that every signed request is served once and before its expiry. Access is not checked.
+	pub fn accept_requester(
+		cache: &mut ReplayCache,
+		requester: &Requester,
+		document_id: u64,
+		now: u64,
+	) -> result::Result<(), types::Error> {
+		if !Self::permissions().has_document(document_id) {
+			return Err(types::Error::DocumentKeyIsNotFound);
+		}
+		Self::ensure_typed_data_domain(requester)?;
+
+		cache.accept(requester, &Self::server_key_id(document_id), now)
+	}
+
+	/// Check that the substrate account of the requester is allowed to retrieve the document key right now.
+	/// Requester must sign the request envelope, bare publics and signatures are not accepted.
+	pub fn check_requester_access(requester: &Requester, document_id: u64) -> result::Result<(), types::Error> {
+		let public = requester.signed_account_public(&Self::server_key_id(document_id))
+			.map_err(types::Error::InsufficientRequesterData)?;
+		Self::check_access(&T::RequesterAccount::convert(public), document_id)
+	}
+
+	/// Serve the signed request of the document, made by the requester at `now` (in seconds): accept it
+	/// with the replay cache of the local key server and check that the requester is allowed to retrieve
+	/// the document key. Accepted request is never served again, even if the access is denied.
+	pub fn serve_requester(requester: &Requester, document_id: u64, now: u64) -> result::Result<(), types::Error> {
+		let mut cache = T::KeyServer::replay_cache();
+		let accepted = Self::accept_requester(&mut cache, requester, document_id, now);
+		T::KeyServer::set_replay_cache(cache);
+		accepted?;
+
+		Self::check_requester_access(requester, document_id)
+	}
+
+	/// Check that the account is allowed to retrieve the document key right now.
+	/// This check guards every path the document key or its shadows are released through.
+	pub fn check_access(who: &T::AccountId, document_id: u64) -> result::Result<(), types::Error> {
+		if Self::key_releases_frozen() {
+			return Err(types::Error::KeyReleaseFrozen);
+		}
+
+		let permissions = Self::permissions();
+		if !permissions.has_document(document_id) {
+			return Err(types::Error::DocumentKeyIsNotFound);
+		}
+		if Self::is_document_frozen(document_id) {
+			return Err(types::Error::KeyReleaseFrozen);
+		}
+		if let Some(schedule) = Self::release_schedule(document_id) {
+			if <system::Module<T>>::block_number() < schedule.release_at {
+				return match permissions.is_document_owner(who, document_id) {
+					true => Ok(()),
+					false => Err(types::Error::AccessDenied),
+				};
+			}
+			if schedule.beneficiaries.map(|beneficiaries| beneficiaries.contains(who)).unwrap_or(true) {
+				return Ok(());
+			}
+		}
+		let now = <system::Module<T>>::block_number();
+		if !permissions.is_address_allowed(who, document_id, &now) && !Self::is_inheritor(who, document_id) {
+			return Err(types::Error::AccessDenied);
+		}
+
+		Ok(())
+	}
+
+	/// Is the account allowed to retrieve the document key?
+	pub fn is_allowed(who: &T::AccountId, document_id: u64) -> bool {
+		Self::check_access(who, document_id).is_ok()
+	}
+
+	/// Does the account have unexpired permission to retrieve the document key?
+	fn has_permission(who: &T::AccountId, document_id: u64) -> bool {
+		Self::permissions().is_address_allowed(who, document_id, &<system::Module<T>>::block_number())
+	}
+
+	/// Retrieve the encrypted document key on behalf of the account. The retrieval is not recorded in the audit trail.
+	pub fn get_encrypted_document_key(
+		who: &T::AccountId,
+		document_id: u64,
+	) -> result::Result<EncryptedDocumentKey, types::Error> {
+		Self::check_access(who, document_id)?;
+
+		Self::permissions().document_key(document_id)
+			.cloned()
+			.ok_or(types::Error::DocumentKeyIsNotFound)
+	}
+
+	/// Has the dead-man's switch of the document triggered in favour of the account?
+	pub fn is_inheritor(who: &T::AccountId, document_id: u64) -> bool {
+		let inheritance = match Self::inheritance(document_id) {
+			Some(inheritance) => inheritance,
+			None => return false,
+		};
+		if !inheritance.beneficiaries.contains(who) {
+			return false;
+		}
+
+		let last_check_in = Self::last_check_in(&inheritance.owner).unwrap_or_else(Zero::zero);
+		<system::Module<T>>::block_number() > last_check_in + inheritance.inactivity_period
+	}
+
+	/// Release the escrowed payment of the buyer to the seller. Returns false if there's no pending purchase.
+	fn settle_purchase(document_id: u64, buyer: &T::AccountId) -> bool {
+		let purchase = match <Purchases<T>>::take((document_id, buyer.clone())) {
+			Some(purchase) => purchase,
+			None => return false,
+		};
+		<DocumentBuyers<T>>::mutate(document_id, |buyers| buyers.retain(|pending| pending != buyer));
+
+		if T::Currency::repatriate_reserved(buyer, &purchase.seller, purchase.price).is_err() {
+			T::Currency::unreserve(buyer, purchase.price);
+		}
+
+		Self::deposit_event(RawEvent::PurchaseSettled(document_id, buyer.clone(), purchase.price));
+		true
+	}
+
+	/// Refund the pending purchase of the buyer, if any.
+	fn refund_purchase(document_id: u64, buyer: &T::AccountId) {
+		if let Some(purchase) = <Purchases<T>>::take((document_id, buyer.clone())) {
+			<DocumentBuyers<T>>::mutate(document_id, |buyers| buyers.retain(|pending| pending != buyer));
+			T::Currency::unreserve(buyer, purchase.price);
+			Self::deposit_event(RawEvent::PurchaseRefunded(document_id, buyer.clone(), purchase.price));
+		}
+	}
+
+	/// Refund all pending purchases of the document. Returns refunded buyers.
+	fn refund_purchases(document_id: u64) -> Vec<T::AccountId> {
+		let buyers = Self::document_buyers(document_id);
+		for buyer in &buyers {
+			Self::refund_purchase(document_id, buyer);
+		}
+		buyers
+	}
+
+	/// Grant access to the subscriber until the block at which the subscription must be renewed.
+	/// Permanent access and access, expiring after the renewal, are left untouched.
+	fn extend_subscription(
+		document_id: u64,
+		subscriber: T::AccountId,
+		plan: SubscriptionPlan<T::AccountId, BalanceOf<T>, T::BlockNumber>,
+		renews_at: T::BlockNumber,
+	) {
+		Self::mutate_permissions(|permissions| {
+			let outlasts_subscription = match permissions.access_expiry(&subscriber, document_id) {
+				Some(expires_at) => *expires_at >= renews_at,
+				None => permissions.is_address_allowed(&subscriber, document_id, &<system::Module<T>>::block_number()),
+			};
+			if !outlasts_subscription {
+				permissions.set_address_allowed_until(&subscriber, document_id, renews_at);
+			}
+		});
+		<SubscriptionRenewals<T>>::mutate(renews_at, |renewals| renewals.push((document_id, subscriber.clone())));
+		<Subscriptions<T>>::insert((document_id, subscriber), Subscription { plan, renews_at });
+	}
+
+	/// Charge subscribers for the next billing period at the agreed price. Subscriptions, which can't be paid
+	/// or which plan has been changed or withdrawn, lapse.
+	fn renew_subscriptions(now: T::BlockNumber) {
+		for (document_id, subscriber) in <SubscriptionRenewals<T>>::take(now) {
+			let subscription = match <Subscriptions<T>>::take((document_id, subscriber.clone())) {
+				Some(subscription) => subscription,
+				None => continue,
+			};
+
+			let renewed = Some(subscription.plan)
+				.filter(|plan| Self::subscription_plan(document_id).as_ref() == Some(plan))
+				.filter(|_| Self::permissions().has_document(document_id) && !Self::is_document_frozen(document_id))
+				.and_then(|plan| T::Currency::transfer(&subscriber, &plan.seller, plan.price).ok().map(|_| plan));
+			match renewed {
+				Some(plan) => {
+					let renews_at = now + plan.period;
+					Self::extend_subscription(document_id, subscriber.clone(), plan, renews_at);
+					Self::deposit_event(RawEvent::SubscriptionRenewed(document_id, subscriber, renews_at));
+				},
+				None => {
+					// only the access, expiring with the subscription, is owned by the subscription
+					Self::mutate_permissions(|permissions| {
+						if permissions.access_expiry(&subscriber, document_id) == Some(&now) {
+							permissions.set_address_disallowed(&subscriber, document_id);
+						}
+					});
+					Self::deposit_event(RawEvent::SubscriptionLapsed(document_id, subscriber));
+				},
+			}
+		}
+	}
+
+	/// Append the record to the audit trail of the document.
+	fn record_audit(document_id: u64, account: T::AccountId, kind: AuditKind) {
+		let block_number = <system::Module<T>>::block_number();
+		let retention = Self::audit_retention() as usize;
+		<AuditTrail<T>>::mutate(document_id, |trail| {
+			trail.push(AuditEntry { account, block_number, kind });
+			if trail.len() > retention {
+				let excess = trail.len() - retention;
+				trail.drain(..excess);
+			}
+		});
+	}
+
+	fn do_check_in(who: T::AccountId) {
+		let now = <system::Module<T>>::block_number();
+		<LastCheckIn<T>>::insert(&who, now);
+
+		Self::deposit_event(RawEvent::CheckedIn(who, now));
+	}
+
+	/// Ensure that the storage has the layout, used by this code.
+	fn ensure_storage_migrated() -> Result {
+		ensure!(Self::storage_version() == migration::CURRENT_STORAGE_VERSION, Error::StorageNotMigrated);
+		Ok(())
+	}
+
+	/// Ensure that the account owns the document.
+	fn ensure_document_owner(who: &T::AccountId, document_id: u64) -> Result {
+		match Self::permissions().document_owners(document_id) {
+			Some(owners) if owners.contains(who) => Ok(()),
+			Some(_) => Err(Error::NotDocumentOwner),
+			None => Err(Error::DocumentKeyIsNotFound),
+		}
+	}
+
+	/// Change the permissions database, keeping track of its size.
+	fn mutate_permissions<R>(f: impl FnOnce(&mut PermissionDatabase<T::AccountId, T::BlockNumber>) -> R) -> R {
+		<Permissions<T>>::mutate(|permissions| {
+			let result = f(permissions);
+			PermissionsSize::put(permissions.size() as u32);
+			result
+		})
+	}
+
+	/// Ensure that the account could change the document without approval of other owners.
+	fn ensure_sole_control(who: &T::AccountId, document_id: u64) -> Result {
+		Self::ensure_document_owner(who, document_id)?;
+		ensure!(
+			Self::permissions().document_approval_threshold(document_id) == Some(1),
+			Error::CoOwnedDocument
+		);
+		Ok(())
+	}
+
+	/// Ensure that the account has sole control over all documents and none of them is frozen.
+	fn ensure_batch_control(who: &T::AccountId, document_ids: &[u64]) -> Result {
+		for document_id in document_ids {
+			Self::ensure_sole_control(who, *document_id)?;
+			ensure!(!Self::is_document_frozen(*document_id), Error::DocumentFrozen);
+		}
+		Ok(())
+	}
+
+	/// Apply the change to the document.
+	fn execute_document_action(document_id: u64, action: DocumentAction<T::AccountId>) -> Result {
+		ensure!(!Self::is_document_frozen(document_id), Error::DocumentFrozen);
+
+		match action {
+			DocumentAction::Grant(account) => {
+				Self::mutate_permissions(|permissions| permissions.set_address_allowed(&account, document_id));
+				if Self::take_access_request(document_id, &account).is_ok() {
+					Self::deposit_event(RawEvent::AccessRequestApproved(document_id, account.clone()));
+				}
+				Self::deposit_event(RawEvent::AccessGranted(document_id, account));
+			},
+			DocumentAction::Revoke(account) => {
+				Self::mutate_permissions(|permissions| permissions.set_address_disallowed(&account, document_id));
+				Self::refund_purchase(document_id, &account);
+				Self::deposit_event(RawEvent::AccessRevoked(document_id, account));
+			},
+			DocumentAction::Delete => Self::remove_document(document_id),
+		}
+
+		Ok(())
+	}
+
+	/// Propose the change of the co-owned document on behalf of the owner, approving it by the owner.
+	fn do_propose_document_action(who: T::AccountId, document_id: u64, action: DocumentAction<T::AccountId>) -> Result {
+		let index = Self::proposal_count(document_id);
+		let expires_at = <system::Module<T>>::block_number() + Self::proposal_ttl();
+		<ProposalCount>::insert(document_id, index + 1);
+		<OpenProposals>::insert(document_id, Self::open_proposals(document_id) + 1);
+		<Proposals<T>>::insert(&document_id, &index, Proposal { action: action.clone(), approvals: Vec::new(), expires_at });
+		<ProposalExpiries<T>>::mutate(expires_at, |proposals| proposals.push((document_id, index)));
+
+		Self::deposit_event(RawEvent::DocumentActionProposed(who.clone(), document_id, index, action));
+		Self::approve_document_action(who, document_id, index)
+	}
+
+	/// Record the owner approval of the proposal, executing the proposal once the threshold is reached.
+	fn approve_document_action(who: T::AccountId, document_id: u64, index: u32) -> Result {
+		let mut proposal = Self::proposal(&document_id, &index).ok_or(Error::ProposalNotFound)?;
+		ensure!(!proposal.approvals.contains(&who), Error::AlreadyApproved);
+		proposal.approvals.push(who.clone());
+
+		let threshold = Self::permissions().document_approval_threshold(document_id).unwrap_or(u32::max_value());
+		if proposal.approvals.len() as u32 >= threshold {
+			Self::execute_document_action(document_id, proposal.action)?;
+			Self::remove_proposal(document_id, index);
+			Self::deposit_event(RawEvent::DocumentActionExecuted(document_id, index));
+		} else {
+			<Proposals<T>>::insert(&document_id, &index, proposal);
+			Self::deposit_event(RawEvent::DocumentActionApproved(who, document_id, index));
+		}
+
+		Ok(())
+	}
+
+	/// Remove the pending access request along with its grant proposal.
+	fn take_access_request(document_id: u64, requester: &T::AccountId) -> Result {
+		let key = (document_id, requester.clone());
+		let request = <AccessRequests<T>>::take(&key).ok_or(Error::NoAccessRequest)?;
+		<AccessRequestExpiries<T>>::mutate(request.expires_at, |requests| requests.retain(|pending| pending != &key));
+		if let Some(index) = request.proposal {
+			Self::remove_proposal(document_id, index);
+		}
+		Ok(())
+	}
+
+	/// Remove the open proposal of the document.
+	fn remove_proposal(document_id: u64, index: u32) {
+		if <Proposals<T>>::take(&document_id, &index).is_none() {
+			return;
+		}
+
+		match Self::open_proposals(document_id).saturating_sub(1) {
+			0 => <OpenProposals>::remove(document_id),
+			count => <OpenProposals>::insert(document_id, count),
+		}
+	}
+
+	/// Drop proposals that have not been approved by enough owners in time.
+	fn expire_proposals(now: T::BlockNumber) {
+		for (document_id, index) in <ProposalExpiries<T>>::take(now) {
+			if <Proposals<T>>::exists(&document_id, &index) {
+				Self::remove_proposal(document_id, index);
+				Self::deposit_event(RawEvent::DocumentActionExpired(document_id, index));
+			}
+		}
+	}
+
+	/// Drop access requests that have not been answered in time, along with their grant proposals.
+	fn expire_access_requests(now: T::BlockNumber) {
+		for (document_id, requester) in <AccessRequestExpiries<T>>::take(now) {
+			if let Some(request) = <AccessRequests<T>>::take(&(document_id, requester.clone())) {
+				if let Some(index) = request.proposal {
+					Self::remove_proposal(document_id, index);
+				}
+				Self::deposit_event(RawEvent::AccessRequestExpired(document_id, requester));
+			}
+		}
+	}
+
+	/// Announce documents, which time locks open at the block.
+	fn open_time_locks(now: T::BlockNumber) {
+		for document_id in <ScheduledReleases<T>>::take(now) {
+			if <ReleaseSchedules<T>>::exists(document_id) {
+				Self::deposit_event(RawEvent::DocumentReleased(document_id));
+			}
+		}
+	}
+
+	/// Remove the document key along with all permissions to retrieve it.
+	fn remove_document(document_id: u64) {
+		Self::mutate_permissions(|permissions| permissions.remove_document(document_id));
+		<Proposals<T>>::remove_prefix(&document_id);
+		<ProposalCount>::remove(document_id);
+		<OpenProposals>::remove(document_id);
+		if let Some(schedule) = <ReleaseSchedules<T>>::take(document_id) {
+			<ScheduledReleases<T>>::mutate(schedule.release_at, |documents| documents.retain(|id| *id != document_id));
+		}
+		<Inheritances<T>>::remove(document_id);
+		for account in <EmergencyAccounts<T>>::take(document_id) {
+			<EmergencyClaims<T>>::remove(&(document_id, account));
+		}
+		<Listings<T>>::remove(document_id);
+		<SubscriptionPlans<T>>::remove(document_id);
+		Self::refund_purchases(document_id);
+		let (dropped, pending): (Vec<_>, Vec<_>) = Self::pending_requests().into_iter().partition(|request| match *request {
+			PendingRequest::DocumentKeyShadowRetrieval { document_id: id, .. } => id == document_id,
+			_ => false,
+		});
+		<PendingRequests<T>>::put(pending);
+		for request in &dropped {
+			Self::forget_request(request);
+		}
+
+		Self::deposit_event(RawEvent::DocumentDeleted(document_id));
+	}
+
+	/// Ensure that the origin is the owner of the online key server.
+	fn ensure_key_server(origin: T::Origin) -> result::Result<NodeId, Error> {
+		let who = ensure_signed(origin)?;
+		let server = Self::bonded_key_server(&who).ok_or(Error::NotKeyServer)?;
+		ensure!(Self::is_key_server_online(&server.node_id), Error::KeyServerOffline);
+		Ok(server.node_id)
+	}
+
+	/// Add the request of the account to the pending requests queue. The request expires after `RequestTtl` blocks.
+	fn file_request(author: &T::AccountId, request: PendingRequest<T::AccountId>) -> Result {
+		let mut requests = Self::pending_requests();
+		ensure!(!requests.contains(&request), Error::RequestAlreadyPending);
+		ensure!((requests.len() as u32) < Self::max_pending_requests(), Error::TooManyPendingRequests);
+		let author_requests = Self::account_pending_requests(author);
+		ensure!(author_requests < Self::max_account_pending_requests(), Error::TooManyAccountPendingRequests);
+
+		let hash = request.hash();
+		let expires_at = <system::Module<T>>::block_number() + Self::request_ttl();
+		<RequestFilings<T>>::insert(&hash, RequestFiling { author: author.clone(), expires_at });
+		<AccountPendingRequests<T>>::insert(author, author_requests + 1);
+		<RequestExpiries<T>>::mutate(expires_at, |expiring| expiring.push(hash));
+		requests.push(request.clone());
+		<PendingRequests<T>>::put(requests);
+
+		Self::deposit_event(RawEvent::RequestFiled(request));
+		Ok(())
+	}
+
+	/// Remove the first pending request, matching the predicate.
+	fn take_request<F>(predicate: F) -> result::Result<PendingRequest<T::AccountId>, Error>
+		where F: Fn(&PendingRequest<T::AccountId>) -> bool
+	{
+		let mut requests = Self::pending_requests();
+		let index = requests.iter().position(predicate).ok_or(Error::NoPendingRequest)?;
+		let request = requests.remove(index);
+		<PendingRequests<T>>::put(requests);
+		Self::forget_request(&request);
+		Ok(request)
+	}
+
+	/// Forget the author, the expiry and the responses of the request, which has left the queue.
+	fn forget_request(request: &PendingRequest<T::AccountId>) {
+		let hash = request.hash();
+		if let Some(filing) = <RequestFilings<T>>::take(&hash) {
+			match Self::account_pending_requests(&filing.author).saturating_sub(1) {
+				0 => <AccountPendingRequests<T>>::remove(&filing.author),
+				count => <AccountPendingRequests<T>>::insert(&filing.author, count),
+			}
+			if <RequestExpiries<T>>::exists(filing.expires_at) {
+				<RequestExpiries<T>>::mutate(filing.expires_at, |expiring| expiring.retain(|pending| *pending != hash));
+			}
+		}
+		for response in RequestResponses::take(&hash) {
+			ResponseVotes::remove(&(hash, response));
+		}
+	}
+
+	/// Drop requests, which have not been serviced in time.
+	fn expire_requests(now: T::BlockNumber) {
+		let expiring = <RequestExpiries<T>>::take(now);
+		if expiring.is_empty() {
+			return;
+		}
+
+		let (expired, pending): (Vec<_>, Vec<_>) = Self::pending_requests()
+			.into_iter()
+			.partition(|request| expiring.contains(&request.hash()));
+		<PendingRequests<T>>::put(pending);
+		for request in expired {
+			Self::forget_request(&request);
+			Self::deposit_event(RawEvent::RequestExpired(request.document_id()));
+		}
+	}
+
+	/// Number of matching responses of the online key servers, required to complete or fail the request.
+	pub fn response_quorum() -> usize {
+		Self::online_key_servers().len() / 2 + 1
+	}
+
+	/// Record the key server response to the first pending request, matching the predicate. Returns the request,
+	/// removed from the queue, once `response_quorum` key servers have submitted the same response, so that
+	/// no single key server could complete or fail the request. Every key server responds only once.
+	fn accept_response<F>(
+		node_id: NodeId,
+		predicate: F,
+		response: &Call<T>,
+	) -> result::Result<Option<PendingRequest<T::AccountId>>, Error>
+		where F: Fn(&PendingRequest<T::AccountId>) -> bool
+	{
+		let request = Self::pending_requests().into_iter().find(|request| predicate(request)).ok_or(Error::NoPendingRequest)?;
+		let request_hash = request.hash();
+		let response_hash: MessageHash = runtime_io::blake2_256(&response.encode()).into();
+		let responses = Self::request_responses(&request_hash);
+		ensure!(
+			responses.iter().all(|response| !Self::response_votes(&(request_hash, *response)).contains(&node_id)),
+			Error::AlreadyResponded
+		);
+
+		let mut votes = Self::response_votes(&(request_hash, response_hash));
+		votes.push(node_id.clone());
+		if votes.len() < Self::response_quorum() {
+			if !responses.contains(&response_hash) {
+				RequestResponses::mutate(&request_hash, |responses| responses.push(response_hash));
+			}
+			ResponseVotes::insert(&(request_hash, response_hash), votes);
+
+			Self::deposit_event(RawEvent::ResponseSubmitted(request.document_id(), node_id));
+			return Ok(None);
+		}
+
+		Self::take_request(|pending| pending == &request).map(Some)
+	}
+
+	/// Store the server key of the document, unless it is already stored. Returns true if the key has been stored.
+	fn store_server_key(document_id: u64, key: ServerKey) -> bool {
+		if <ServerKeys>::exists(document_id) {
+			return false;
+		}
+
+		<ServerKeys>::insert(document_id, key);
+		true
+	}
+
+	fn is_shadow_retrieval_request(request: &PendingRequest<T::AccountId>, document_id: u64, requester: &T::AccountId) -> bool {
+		match *request {
+			PendingRequest::DocumentKeyShadowRetrieval { document_id: id, requester: ref author, .. } =>
+				id == document_id && author == requester,
+			_ => false,
+		}
+	}
+
+	/// Service pending requests, which haven't been serviced yet, with the local key server, producing
+	/// the response calls along with the request hashes. Returns nothing if this node does not run
+	/// an online key server.
+	pub fn service_pending_requests() -> Vec<(MessageHash, Call<T>)> {
+		let node_id = match T::KeyServer::node_id() {
+			Some(node_id) => node_id,
+			None => return Vec::new(),
+		};
+		if !Self::is_key_server_online(&node_id) {
+			return Vec::new();
+		}
+
+		let serviced = T::KeyServer::serviced_requests();
+		Self::pending_requests()
+			.into_iter()
+			.map(|request| (request.hash(), request))
+			.filter(|(hash, _)| !serviced.contains(hash))
+			.filter_map(|(hash, request)| Self::service_request(request).map(|response| (hash, response)))
+			.collect()
+	}
+
+	/// Service the request with the local key server. Shadow retrieval requests are left pending
+	/// while key releases are frozen.
+	fn service_request(request: PendingRequest<T::AccountId>) -> Option<Call<T>> {
+		if let PendingRequest::DocumentKeyShadowRetrieval { document_id, ref requester, .. } = request {
+			match Self::check_access(requester, document_id) {
+				Ok(()) => (),
+				Err(types::Error::KeyReleaseFrozen) => return None,
+				Err(_) => return Some(Call::request_failed(request.clone())),
+			}
+		}
+
+		let key_id = Self::server_key_id(request.document_id());
+		let response = match request {
+			PendingRequest::ServerKeyGeneration { document_id, threshold, .. } =>
+				T::KeyServer::generate_server_key(&key_id, threshold)
+					.map(|public| Call::server_key_generated(document_id, public)),
+			PendingRequest::ServerKeyRetrieval { document_id } =>
+				T::KeyServer::restore_server_key(&key_id)
+					.map(|(public, threshold)| Call::server_key_retrieved(document_id, public, threshold)),
+			PendingRequest::DocumentKeyShadowRetrieval { document_id, ref requester, ref requester_public } =>
+				T::KeyServer::restore_document_key_shadow(&key_id, requester_public)
+					.and_then(|shadow| match (shadow.common_point, shadow.decrypt_shadows) {
+						(Some(common_point), Some(decrypt_shadows)) => Ok(Call::document_key_shadow_retrieved(
+							document_id,
+							requester.clone(),
+							shadow.decrypted_secret,
+							common_point,
+							decrypt_shadows,
+						)),
+						_ => Err(types::Error::Internal("key server has returned incomplete shadow".into())),
+					}),
+		};
+
+		Some(response.unwrap_or_else(|_| Call::request_failed(request)))
+	}
+
+	/// Service pending requests with the local key server and submit the responses. Requests stay
+	/// serviced until they leave the queue, so every request is only serviced once.
+	pub fn submit_pending_responses() {
+		let pending: Vec<_> = Self::pending_requests().iter().map(PendingRequest::hash).collect();
+		let mut serviced: Vec<_> = T::KeyServer::serviced_requests()
+			.into_iter()
+			.filter(|hash| pending.contains(hash))
+			.collect();
+		for (hash, response) in Self::service_pending_requests() {
+			match T::SubmitResponse::submit_response(response) {
+				Ok(()) => serviced.push(hash),
+				Err(()) => runtime_io::print("failed to submit secret store response"),
+			}
+		}
+		T::KeyServer::set_serviced_requests(serviced);
+	}
+}
+
+decl_event!(
+	pub enum Event<T> where
+		AccountId = <T as system::Trait>::AccountId,
+		BlockNumber = <T as system::Trait>::BlockNumber,
+		Balance = BalanceOf<T>,
+	{
+		// Just a dummy event.
+		// Event `Something` is declared with a parameter of the type `u32` and `AccountId`
+		// To emit this event, we call the deposit funtion, from our runtime funtions
+		SomethingStored(u32, AccountId),
+		/// Key server has been registered by the account.
+		KeyServerRegistered(AccountId, NodeId),
+		/// Key server has been removed from the set by its owner. Its bond is unlocked at the block.
+		KeyServerUnregistered(AccountId, NodeId, BlockNumber),
+		/// Bond of the key server, which has left the set, has been released to the owner.
+		BondWithdrawn(AccountId, Balance),
+		/// Key server (reporter account, offender) misbehaviour has been reported.
+		MisbehaviourReported(AccountId, NodeId, MisbehaviourKind),
+		/// Key server has been slashed and removed from the set.
+		KeyServerSlashed(AccountId, NodeId, Balance),
+		/// Key server has missed its heartbeats and is considered offline.
+		KeyServerOffline(NodeId),
+		/// Offline key server has submitted a heartbeat.
+		KeyServerOnline(NodeId),
+		/// Document key has been stored by the account.
+		DocumentKeyStored(AccountId, u64),
+		/// Account has retrieved the encrypted document key.
+		DocumentKeyRetrieved(AccountId, u64, EncryptedDocumentKey),
+		/// Account has been allowed to retrieve the document key.
+		AccessGranted(u64, AccountId),
+		/// Account has been disallowed to retrieve the document key.
+		AccessRevoked(u64, AccountId),
+		/// Document key has been deleted.
+		DocumentDeleted(u64),
+		/// Request has been filed for the key servers.
+		RequestFiled(PendingRequest<AccountId>),
+		/// Server key has been generated for the document.
+		ServerKeyGenerated(u64, Public),
+		/// Server key of the document has been retrieved.
+		ServerKeyRetrieved(u64, Public),
+		/// Document key shadow has been retrieved (requester, document, decrypted secret, common point, shadows).
+		DocumentKeyShadowRetrieved(AccountId, u64, Public, Public, Vec<Vec<u8>>),
+		/// Key servers have failed to service the request for the document.
+		RequestFailed(u64),
+		/// Key servers have not serviced the request for the document in time.
+		RequestExpired(u64),
+		/// Key server has responded to the request for the document, waiting for other key servers to agree.
+		ResponseSubmitted(u64, NodeId),
+		/// Document has been frozen by the administrator.
+		DocumentFrozen(u64),
+		/// Document has been unfrozen by the administrator.
+		DocumentUnfrozen(u64),
+		/// Parameters of the key servers set have been updated by the administrator.
+		ServerSetParametersUpdated,
+		/// Administrators policy has been changed.
+		AdminPolicyChanged,
+		/// Admin operation with the given nonce has been authorized by the administrators and executed.
+		AdminCallExecuted(u64),
+		/// Release of all document keys and shadows has been halted.
+		KeyReleasesFrozen,
+		/// Release of document keys and shadows has been resumed.
+		KeyReleasesUnfrozen,
+		/// Account has requested access to the document, attaching the message hash.
+		AccessRequested(AccountId, u64, Option<MessageHash>),
+		/// Owner has approved the access request of the account.
+		AccessRequestApproved(u64, AccountId),
+		/// Owner has rejected the access request of the account.
+		AccessRequestRejected(u64, AccountId),
+		/// Access request of the account has not been answered in time.
+		AccessRequestExpired(u64, AccountId),
+		/// Owner has proposed the change of the co-owned document (owner, document, proposal index, change).
+		DocumentActionProposed(AccountId, u64, u32, DocumentAction<AccountId>),
+		/// Owner has approved the proposed change of the co-owned document.
+		DocumentActionApproved(AccountId, u64, u32),
+		/// Proposed change of the co-owned document has been approved by enough owners and executed.
+		DocumentActionExecuted(u64, u32),
+		/// Proposed change of the co-owned document has not been approved by enough owners in time.
+		DocumentActionExpired(u64, u32),
+		/// Document key has been time locked until the block.
+		ReleaseScheduled(u64, BlockNumber),
+		/// Time lock of the document key has opened.
+		DocumentReleased(u64),
+		/// Dead-man's switch of the document has been set by the owner.
+		InheritanceSet(u64, AccountId),
+		/// Dead-man's switch of the document has been removed.
+		InheritanceRemoved(u64),
+		/// Account has checked in at the block.
+		CheckedIn(AccountId, BlockNumber),
+		/// Emergency accounts of the document have been changed.
+		EmergencyAccountsSet(u64),
+		/// Account has claimed emergency access to the document, which could be completed at the block.
+		EmergencyAccessClaimed(u64, AccountId, BlockNumber),
+		/// Owner has vetoed the emergency access claim of the account.
+		EmergencyAccessVetoed(u64, AccountId),
+		/// Account has been granted emergency access to the document.
+		EmergencyAccessGranted(u64, AccountId),
+		/// Document key has been replaced by the owner.
+		DocumentRekeyed(u64),
+		/// Access to the document key has been offered for sale at the price.
+		DocumentListed(u64, Balance),
+		/// Access to the document key is no longer for sale.
+		DocumentUnlisted(u64),
+		/// Account has bought access to the document key, paying the price into escrow.
+		AccessPurchased(u64, AccountId, Balance),
+		/// Escrowed payment of the account has been released to the seller.
+		PurchaseSettled(u64, AccountId, Balance),
+		/// Escrowed payment has been returned to the account.
+		PurchaseRefunded(u64, AccountId, Balance),
+		/// Account has been allowed to retrieve the document key until the block.
+		TemporaryAccessGranted(u64, AccountId, BlockNumber),
+		/// Access to the document key has been offered by subscription (price, billing period).
+		SubscriptionOffered(u64, Balance, BlockNumber),
+		/// Subscription offer has been withdrawn.
+		SubscriptionOfferWithdrawn(u64),
+		/// Account has subscribed to the document key access until the block.
+		Subscribed(u64, AccountId, BlockNumber),
+		/// Subscription has been paid for the next billing period, until the block.
+		SubscriptionRenewed(u64, AccountId, BlockNumber),
+		/// Subscription hasn't been paid and the access has lapsed.
+		SubscriptionLapsed(u64, AccountId),
+		/// Account has cancelled the subscription.
+		Unsubscribed(u64, AccountId),
+		/// Public key of the address has been published.
+		PublicPublished(Address),
+		/// Encryption key has been added to the account.
+		EncryptionKeyAdded(AccountId, Public),
+		/// Encryption key has been removed from the account.
+		EncryptionKeyRemoved(AccountId, Public),
+		/// Encryption key of the account has been replaced with the new key.
+		EncryptionKeyRotated(AccountId, Public, Public),
+	}
+);
+
+/// tests for this module
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	use runtime_io::with_externalities;
+	use primitives::{H256, Blake2Hasher};
+	use support::{impl_outer_origin, assert_ok, assert_noop, parameter_types};
+	use sr_primitives::traits::{OnInitialize, OnFinalize};
+	use sr_primitives::{traits::{BlakeTwo256, IdentityLookup, ConvertInto}, testing::Header};
+	use sr_primitives::weights::Weight;
+	use sr_primitives::Perbill;
+	use sr_primitives::traits::Dispatchable;
+	use parity_crypto::publickey::{Generator, KeyPair, Random};
+	use std::cell::RefCell;
+
+	impl_outer_origin! {
+		pub enum Origin for Test {}
+	}
+
+	// For testing the module, we construct most of a mock runtime. This means
+	// first constructing a configuration type (`Test`) which `impl`s each of the
+	// configuration traits of modules we want to use.
+	#[derive(Clone, Eq, PartialEq)]
+	pub struct Test;
+	parameter_types! {
+		pub const BlockHashCount: u64 = 250;
+		pub const MaximumBlockWeight: Weight = 1024;
+		pub const MaximumBlockLength: u32 = 2 * 1024;
+		pub const AvailableBlockRatio: Perbill = Perbill::from_percent(75);
+	}
+	impl system::Trait for Test {
+		type Origin = Origin;
+		type Call = ();
+		type Index = u64;
+		type BlockNumber = u64;
+		type Hash = H256;
+		type Hashing = BlakeTwo256;
+		type AccountId = u64;
+		type Lookup = IdentityLookup<Self::AccountId>;
+		type Header = Header;
+		type WeightMultiplierUpdate = ();
+		type Event = ();
+		type BlockHashCount = BlockHashCount;
+		type MaximumBlockWeight = MaximumBlockWeight;
+		type MaximumBlockLength = MaximumBlockLength;
+		type AvailableBlockRatio = AvailableBlockRatio;
+		type Version = ();
+	}
+	parameter_types! {
+		pub const ExistentialDeposit: u64 = 0;
+		pub const TransferFee: u64 = 0;
+		pub const CreationFee: u64 = 0;
+		pub const TransactionBaseFee: u64 = 0;
+		pub const TransactionByteFee: u64 = 0;
+	}
+	impl balances::Trait for Test {
+		type Balance = u64;
+		type OnFreeBalanceZero = ();
+		type OnNewAccount = ();
+		type Event = ();
+		type TransactionPayment = ();
+		type TransferPayment = ();
+		type DustRemoval = ();
+		type ExistentialDeposit = ExistentialDeposit;
+		type TransferFee = TransferFee;
+		type CreationFee = CreationFee;
+		type TransactionBaseFee = TransactionBaseFee;
+		type TransactionByteFee = TransactionByteFee;
+		type WeightToFee = ConvertInto;
+	}
+	impl Trait for Test {
+		type Event = ();
+		type EncryptedDocumentKey = bytes::Bytes;
+		type Currency = Balances;
+		type Slash = ();
+		type KeyServer = TestKeyServer;
+		type VerifyMisbehaviour = TestKeyServer;
+		type SubmitResponse = TestKeyServer;
+		type AdminOrigin = system::EnsureRoot<u64>;
+		type RequesterAccount = TestRequesterAccount;
+	}
+
+	/// Maps substrate publics to the test accounts, which are `u64`.
+	pub struct TestRequesterAccount;
+
+	impl Convert<[u8; 32], u64> for TestRequesterAccount {
+		fn convert(public: [u8; 32]) -> u64 {
+			u64::decode(&mut &public[..]).expect("public is longer than u64; qed")
+		}
+	}
+
+	thread_local! {
+		static LOCAL_KEY_SERVER: RefCell<Option<NodeId>> = RefCell::new(None);
+		static SUBMITTED_RESPONSES: RefCell<Vec<Call<Test>>> = RefCell::new(Vec::new());
+		static SERVICED_REQUESTS: RefCell<Vec<MessageHash>> = RefCell::new(Vec::new());
+		static REPLAY_CACHE: RefCell<ReplayCache> = RefCell::new(ReplayCache::new(60));
+	}
+
+	/// Key server, which is running in-process instead of talking to the cluster.
+	pub struct TestKeyServer;
+
+	impl TestKeyServer {
+		fn run_as(node_id: NodeId) {
+			LOCAL_KEY_SERVER.with(|local| *local.borrow_mut() = Some(node_id));
+		}
+
+		fn take_responses() -> Vec<Call<Test>> {
+			SUBMITTED_RESPONSES.with(|responses| responses.borrow_mut().drain(..).collect())
+		}
+
+		fn server_key_public(key_id: &ServerKeyId) -> Public {
+			Public::from_low_u64_be(key_id.to_low_u64_be())
+		}
+	}
+
+	impl KeyServer for TestKeyServer {
+		fn node_id() -> Option<NodeId> {
+			LOCAL_KEY_SERVER.with(|local| local.borrow().clone())
+		}
+
+		fn generate_server_key(key_id: &ServerKeyId, _threshold: u8) -> result::Result<Public, types::Error> {
+			Ok(Self::server_key_public(key_id))
+		}
+
+		fn restore_server_key(key_id: &ServerKeyId) -> result::Result<(Public, u8), types::Error> {
+			if key_id.is_zero() {
+				Err(types::Error::ServerKeyIsNotFound)
+			} else {
+				Ok((Self::server_key_public(key_id), 1))
+			}
+		}
+
+		fn restore_document_key_shadow(
+			key_id: &ServerKeyId,
+			requester: &Public,
+		) -> result::Result<EncryptedDocumentKeyShadow, types::Error> {
+			Ok(EncryptedDocumentKeyShadow {
+				decrypted_secret: Self::server_key_public(key_id),
+				common_point: Some(requester.clone()),
+				decrypt_shadows: Some(vec![requester.as_bytes().to_vec()]),
+			})
+		}
+
+		fn serviced_requests() -> Vec<MessageHash> {
+			SERVICED_REQUESTS.with(|serviced| serviced.borrow().clone())
+		}
+
+		fn set_serviced_requests(requests: Vec<MessageHash>) {
+			SERVICED_REQUESTS.with(|serviced| *serviced.borrow_mut() = requests);
+		}
+
+		fn replay_cache() -> ReplayCache {
+			REPLAY_CACHE.with(|cache| cache.borrow().clone())
+		}
+
+		fn set_replay_cache(cache: ReplayCache) {
+			REPLAY_CACHE.with(|replay_cache| *replay_cache.borrow_mut() = cache);
+		}
+	}
+
+	impl VerifyMisbehaviour for TestKeyServer {
+		fn is_invalid(_kind: &MisbehaviourKind, message: &[u8]) -> bool {
+			message != &b"valid"[..]
+		}
+	}
+
+	impl SubmitResponse<Test> for TestKeyServer {
+		fn submit_response(response: Call<Test>) -> result::Result<(), ()> {
+			SUBMITTED_RESPONSES.with(|responses| responses.borrow_mut().push(response));
+			Ok(())
+		}
+	}
+	type TemplateModule = Module<Test>;
+	type System = system::Module<Test>;
+	type Balances = balances::Module<Test>;
+
+	const KEY_SERVER_BOND: u64 = 100;
+	const HEARTBEAT_TIMEOUT: u64 = 10;
+	const ACCESS_REQUEST_TTL: u64 = 5;
+	const PROPOSAL_TTL: u64 = 20;
+	const EMERGENCY_ACCESS_DELAY: u64 = 5;
+	const AUDIT_RETENTION: u32 = 3;
+	const MAX_ENCRYPTION_KEYS: u32 = 2;
+	const MAX_PENDING_REQUESTS: u32 = 4;
+	const UNBONDING_PERIOD: u64 = 10;
+	const MAX_ACCOUNT_PENDING_REQUESTS: u32 = 2;
+	const REQUEST_TTL: u64 = 10;
+
+	// This function basically just builds a genesis storage key/value store according to
+	// our desired mockup.
+	fn new_test_ext() -> runtime_io::TestExternalities<Blake2Hasher> {
+		let mut t = system::GenesisConfig::default().build_storage::<Test>().unwrap();
+		balances::GenesisConfig::<Test> {
+			balances: (1..=5).map(|account| (account, 1_000)).collect(),
+			vesting: vec![],
+		}.assimilate_storage(&mut t).unwrap();
+		GenesisConfig::<Test> {
+			key_server_bond: KEY_SERVER_BOND,
+			misbehaviour_report_threshold: 2,
+			heartbeat_timeout: HEARTBEAT_TIMEOUT,
+			access_request_ttl: ACCESS_REQUEST_TTL,
+			proposal_ttl: PROPOSAL_TTL,
+			emergency_access_delay: EMERGENCY_ACCESS_DELAY,
+			audit_retention: AUDIT_RETENTION,
+			max_encryption_keys: MAX_ENCRYPTION_KEYS,
+			max_pending_requests: MAX_PENDING_REQUESTS,
+			unbonding_period: UNBONDING_PERIOD,
+			max_account_pending_requests: MAX_ACCOUNT_PENDING_REQUESTS,
+			request_ttl: REQUEST_TTL,
+		}.assimilate_storage(&mut t).unwrap();
+		t.into()
+	}
+
+	fn key_pair() -> KeyPair {
+		Random.generate().unwrap()
+	}
+
+	fn signed_evidence(offender: &KeyPair, message: &[u8]) -> Misbehaviour {
+		let signature = parity_crypto::publickey::sign(offender.secret(), &Misbehaviour::message_hash(message)).unwrap();
+		Misbehaviour {
+			kind: MisbehaviourKind::InvalidShadow,
+			message: message.to_vec(),
+			signature: signature.to_vec(),
+		}
+	}
+
+	fn evidence_from(offender: &KeyPair) -> Misbehaviour {
+		signed_evidence(offender, b"invalid")
+	}
+
+	fn signed_heartbeat(node: &KeyPair, block_number: u64) -> (Heartbeat<u64>, Vec<u8>) {
+		let heartbeat = Heartbeat { node_id: node.public().clone(), block_number };
+		let signature = parity_crypto::publickey::sign(node.secret(), &heartbeat.hash()).unwrap();
+		(heartbeat, signature.to_vec())
+	}
+
+	#[test]
+	fn it_works_for_default_value() {
+		with_externalities(&mut new_test_ext(), || {
+			// Just a dummy test for the dummy funtion `do_something`
+			// calling the `do_something` function with a value 42
+			assert_ok!(TemplateModule::do_something(Origin::signed(1), 42));
+			// asserting that the stored value is equal to what we stored
+			assert_eq!(TemplateModule::something(), Some(42));
+		});
+	}
+
+	#[test]
+	fn key_server_registration_bonds_funds() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+			assert_eq!(Balances::reserved_balance(1), KEY_SERVER_BOND);
+			assert_eq!(TemplateModule::key_servers(), vec![node.public().clone()]);
+			assert_noop!(
+				TemplateModule::register_key_server(Origin::signed(2), node.public().clone()),
+				Error::KeyServerAlreadyRegistered
+			);
+
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::unregister_key_server(Origin::signed(1)));
+			assert!(TemplateModule::key_servers().is_empty());
+			assert_eq!(Balances::reserved_balance(1), KEY_SERVER_BOND);
+			assert_noop!(
+				TemplateModule::register_key_server(Origin::signed(1), key_pair().public().clone()),
+				Error::StillUnbonding
+			);
+			assert_noop!(TemplateModule::withdraw_bond(Origin::signed(2)), Error::NotUnbonding);
+
+			System::set_block_number(UNBONDING_PERIOD);
+			assert_noop!(TemplateModule::withdraw_bond(Origin::signed(1)), Error::BondLocked);
+			System::set_block_number(1 + UNBONDING_PERIOD);
+			assert_ok!(TemplateModule::withdraw_bond(Origin::signed(1)));
+			assert_eq!(Balances::reserved_balance(1), 0);
+			assert_eq!(TemplateModule::key_server_owner(node.public()), None);
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(2), node.public().clone()));
+		});
+	}
+
+	#[test]
+	fn unregistered_key_server_is_slashed_during_unbonding() {
+		with_externalities(&mut new_test_ext(), || {
+			let nodes: Vec<_> = (0..3).map(|_| key_pair()).collect();
+			for (account, node) in (1..).zip(nodes.iter()) {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(account), node.public().clone()));
+			}
+
+			let offender = nodes[0].public().clone();
+			assert_ok!(TemplateModule::unregister_key_server(Origin::signed(1)));
+			assert_ok!(TemplateModule::report_misbehaviour(Origin::signed(2), offender.clone(), evidence_from(&nodes[0])));
+			assert_ok!(TemplateModule::report_misbehaviour(Origin::signed(3), offender.clone(), evidence_from(&nodes[0])));
+
+			assert_eq!(Balances::reserved_balance(1), 0);
+			assert_eq!(Balances::free_balance(1), 1_000 - KEY_SERVER_BOND);
+			assert_eq!(TemplateModule::unbonding_key_server(1), None);
+			System::set_block_number(1 + UNBONDING_PERIOD);
+			assert_noop!(TemplateModule::withdraw_bond(Origin::signed(1)), Error::NotUnbonding);
+		});
+	}
+
+	#[test]
+	fn misbehaving_key_server_is_slashed_once_threshold_is_reached() {
+		with_externalities(&mut new_test_ext(), || {
+			let nodes: Vec<_> = (0..3).map(|_| key_pair()).collect();
+			for (account, node) in (1..).zip(nodes.iter()) {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(account), node.public().clone()));
+			}
+
+			let offender = nodes[0].public().clone();
+			assert_noop!(
+				TemplateModule::report_misbehaviour(Origin::signed(2), offender.clone(), evidence_from(&nodes[1])),
+				Error::InvalidEvidence
+			);
+			assert_noop!(
+				TemplateModule::report_misbehaviour(Origin::signed(4), offender.clone(), evidence_from(&nodes[0])),
+				Error::NotKeyServer
+			);
+			assert_noop!(
+				TemplateModule::report_misbehaviour(Origin::signed(2), offender.clone(), signed_evidence(&nodes[0], b"valid")),
+				Error::InvalidEvidence
+			);
+
+			// heartbeat signatures are stored on chain, but can't be replayed as the evidence
+			let (heartbeat, signature) = signed_heartbeat(&nodes[0], 1);
+			let replayed = Misbehaviour {
+				kind: MisbehaviourKind::InvalidMessage,
+				message: heartbeat.encode(),
+				signature,
+			};
+			assert_noop!(
+				TemplateModule::report_misbehaviour(Origin::signed(2), offender.clone(), replayed),
+				Error::InvalidEvidence
+			);
+
+			assert_ok!(TemplateModule::report_misbehaviour(Origin::signed(2), offender.clone(), evidence_from(&nodes[0])));
+			assert_eq!(Balances::reserved_balance(1), KEY_SERVER_BOND);
+			assert_ok!(TemplateModule::report_misbehaviour(Origin::signed(3), offender.clone(), evidence_from(&nodes[0])));
+
+			assert_eq!(Balances::reserved_balance(1), 0);
+			assert_eq!(Balances::free_balance(1), 1_000 - KEY_SERVER_BOND);
+			assert!(!TemplateModule::key_servers().contains(&offender));
+			assert_eq!(TemplateModule::key_server_owner(&offender), None);
+		});
+	}
+
+	#[test]
+	#[should_panic(expected = "misbehaviour report threshold must be nonzero")]
+	fn zero_report_threshold_is_rejected_at_genesis() {
+		let _ = GenesisConfig::<Test> {
+			key_server_bond: KEY_SERVER_BOND,
+			misbehaviour_report_threshold: 0,
+			heartbeat_timeout: HEARTBEAT_TIMEOUT,
+			access_request_ttl: ACCESS_REQUEST_TTL,
+			proposal_ttl: PROPOSAL_TTL,
+			emergency_access_delay: EMERGENCY_ACCESS_DELAY,
+			audit_retention: AUDIT_RETENTION,
+			max_encryption_keys: MAX_ENCRYPTION_KEYS,
+			max_pending_requests: MAX_PENDING_REQUESTS,
+			unbonding_period: UNBONDING_PERIOD,
+			max_account_pending_requests: MAX_ACCOUNT_PENDING_REQUESTS,
+			request_ttl: REQUEST_TTL,
+		}.build_storage();
+	}
+
+	#[test]
+	fn key_server_goes_offline_without_heartbeats() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			let node_id = node.public().clone();
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node_id.clone()));
+
+			assert_eq!(TemplateModule::heartbeat_deadlines(2 + HEARTBEAT_TIMEOUT), vec![node_id.clone()]);
+			TemplateModule::on_initialize(1 + HEARTBEAT_TIMEOUT);
+			assert!(TemplateModule::is_key_server_online(&node_id));
+			TemplateModule::on_initialize(2 + HEARTBEAT_TIMEOUT);
+			assert!(!TemplateModule::is_key_server_online(&node_id));
+			assert_eq!(TemplateModule::offline_key_servers(), vec![node_id.clone()]);
+			assert!(TemplateModule::heartbeat_deadlines(2 + HEARTBEAT_TIMEOUT).is_empty());
+
+			System::set_block_number(20);
+			let (heartbeat, signature) = signed_heartbeat(&node, 20);
+			assert_noop!(
+				TemplateModule::heartbeat(Origin::signed(2), heartbeat.clone(), signature.clone()),
+				Error::NotKeyServerOwner
+			);
+			assert_ok!(TemplateModule::heartbeat(Origin::signed(1), heartbeat.clone(), signature.clone()));
+			assert!(TemplateModule::is_key_server_online(&node_id));
+			assert_eq!(TemplateModule::last_seen(&node_id), Some(20));
+			assert_noop!(
+				TemplateModule::heartbeat(Origin::signed(1), heartbeat, signature),
+				Error::OutdatedHeartbeat
+			);
+		});
+	}
+
+	#[test]
+	fn only_key_servers_with_passed_deadlines_are_checked() {
+		with_externalities(&mut new_test_ext(), || {
+			let (first, second) = (key_pair(), key_pair());
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), first.public().clone()));
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(2), second.public().clone()));
+
+			// the first server has sent the heartbeat, so its old deadline is skipped and the new one is checked later
+			System::set_block_number(5);
+			let (heartbeat, signature) = signed_heartbeat(&first, 5);
+			assert_ok!(TemplateModule::heartbeat(Origin::signed(1), heartbeat, signature));
+			assert_eq!(TemplateModule::heartbeat_deadlines(6 + HEARTBEAT_TIMEOUT), vec![first.public().clone()]);
+
+			System::set_block_number(2 + HEARTBEAT_TIMEOUT);
+			TemplateModule::on_initialize(2 + HEARTBEAT_TIMEOUT);
+			assert!(TemplateModule::is_key_server_online(first.public()));
+			assert_eq!(TemplateModule::offline_key_servers(), vec![second.public().clone()]);
+
+			// increased timeout postpones the check
+			assert_ok!(TemplateModule::set_server_set_parameters(Origin::ROOT, None, None, Some(2 * HEARTBEAT_TIMEOUT)));
+			System::set_block_number(6 + HEARTBEAT_TIMEOUT);
+			TemplateModule::on_initialize(6 + HEARTBEAT_TIMEOUT);
+			assert!(TemplateModule::is_key_server_online(first.public()));
+			assert_eq!(TemplateModule::heartbeat_deadlines(6 + 2 * HEARTBEAT_TIMEOUT), vec![first.public().clone()]);
+		});
+	}
+
+	#[test]
+	fn heartbeat_must_be_signed_by_the_key_server() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+
+			let (heartbeat, _) = signed_heartbeat(&node, 0);
+			let (_, foreign_signature) = signed_heartbeat(&key_pair(), 0);
+			assert_noop!(
+				TemplateModule::heartbeat(Origin::signed(1), heartbeat, foreign_signature),
+				Error::InvalidHeartbeatSignature
+			);
+		});
+	}
+
+	#[test]
+	fn document_key_access_is_controlled_by_the_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			let key: EncryptedDocumentKey = vec![1, 2, 3];
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, key.clone()));
+			assert_noop!(
+				TemplateModule::store_document_key(Origin::signed(2), 7, key.clone()),
+				Error::DocumentKeyAlreadyStored
+			);
+			assert_noop!(
+				TemplateModule::grant_access(Origin::signed(2), 7, 2),
+				Error::NotDocumentOwner
+			);
+
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Err(types::Error::AccessDenied));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Ok(key.clone()));
+			assert_ok!(TemplateModule::revoke_access(Origin::signed(1), 7, 2));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Err(types::Error::AccessDenied));
+
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+			assert_ok!(TemplateModule::delete_document(Origin::signed(1), 7));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&1, 7), Err(types::Error::DocumentKeyIsNotFound));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn offchain_worker_services_pending_requests() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			let requester = key_pair();
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+			TestKeyServer::run_as(node.public().clone());
+
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(2), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(2), 7, 3));
+			assert_noop!(TemplateModule::generate_server_key(Origin::signed(3), 7, 1), Error::NotDocumentOwner);
+			assert_noop!(TemplateModule::generate_server_key(Origin::signed(3), 8, 1), Error::DocumentKeyIsNotFound);
+			assert_ok!(TemplateModule::generate_server_key(Origin::signed(2), 7, 1));
+			assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(3), 7, requester.public().clone()));
+			assert_noop!(TemplateModule::retrieve_server_key(Origin::signed(4), 8), Error::DocumentKeyIsNotFound);
+			assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(4), 7));
+			assert_noop!(
+				TemplateModule::retrieve_document_key_shadow(Origin::signed(4), 7, requester.public().clone()),
+				Error::AccessDenied
+			);
+			assert_eq!(TemplateModule::pending_requests().len(), 3);
+
+			TemplateModule::submit_pending_responses();
+			let responses = TestKeyServer::take_responses();
+			assert_eq!(responses.len(), 3);
+
+			// requests are serviced once, while the responses are waiting to be included
+			TemplateModule::submit_pending_responses();
+			assert!(TestKeyServer::take_responses().is_empty());
+
+			for response in responses.clone() {
+				assert_noop!(response.dispatch(Origin::signed(2)), Error::NotKeyServer);
+			}
+			for response in responses {
+				assert_ok!(response.dispatch(Origin::signed(1)));
+			}
+
+			assert!(TemplateModule::pending_requests().is_empty());
+			assert_eq!(
+				TemplateModule::server_key(7),
+				Some(ServerKey { public: TestKeyServer::server_key_public(&TemplateModule::server_key_id(7)), threshold: 1 })
+			);
+		});
+	}
+
+	#[test]
+	fn offchain_worker_does_nothing_without_local_key_server() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(2), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::generate_server_key(Origin::signed(2), 7, 1));
+
+			TemplateModule::submit_pending_responses();
+			assert!(TestKeyServer::take_responses().is_empty());
+			assert_eq!(TemplateModule::pending_requests().len(), 1);
+		});
+	}
+
+	#[test]
+	fn pending_requests_queue_is_bounded() {
+		with_externalities(&mut new_test_ext(), || {
+			for document_id in 0..MAX_PENDING_REQUESTS as u64 + 1 {
+				assert_ok!(TemplateModule::store_document_key(Origin::signed(1), document_id, vec![1, 2, 3]));
+			}
+			for document_id in 0..MAX_ACCOUNT_PENDING_REQUESTS as u64 {
+				assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(2), document_id));
+			}
+			assert_noop!(
+				TemplateModule::retrieve_server_key(Origin::signed(2), MAX_ACCOUNT_PENDING_REQUESTS as u64),
+				Error::TooManyAccountPendingRequests
+			);
+			for document_id in MAX_ACCOUNT_PENDING_REQUESTS as u64..MAX_PENDING_REQUESTS as u64 {
+				assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(3), document_id));
+			}
+
+			assert_noop!(
+				TemplateModule::retrieve_server_key(Origin::signed(4), MAX_PENDING_REQUESTS as u64),
+				Error::TooManyPendingRequests
+			);
+		});
+	}
+
+	#[test]
+	fn stale_requests_expire() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(2), 7));
+			assert_eq!(TemplateModule::account_pending_requests(2), 1);
+
+			TemplateModule::on_finalize(REQUEST_TTL);
+			assert_eq!(TemplateModule::pending_requests().len(), 1);
+			TemplateModule::on_finalize(1 + REQUEST_TTL);
+			assert!(TemplateModule::pending_requests().is_empty());
+			assert_eq!(TemplateModule::account_pending_requests(2), 0);
+			assert!(TemplateModule::request_expiries(1 + REQUEST_TTL).is_empty());
+
+			assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(2), 7));
+		});
+	}
+
+	#[test]
+	fn responses_are_accepted_once_the_majority_of_key_servers_agrees() {
+		with_externalities(&mut new_test_ext(), || {
+			let nodes: Vec<_> = (0..3).map(|_| key_pair()).collect();
+			for (account, node) in (1..).zip(nodes.iter()) {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(account), node.public().clone()));
+			}
+			assert_eq!(TemplateModule::response_quorum(), 2);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(4), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(4), 7));
+
+			// single key server can neither fail the request, nor choose the key
+			let request = PendingRequest::ServerKeyRetrieval { document_id: 7 };
+			assert_ok!(TemplateModule::request_failed(Origin::signed(1), request.clone()));
+			assert_noop!(
+				TemplateModule::server_key_retrieved(Origin::signed(1), 7, Public::from_low_u64_be(1), 1),
+				Error::AlreadyResponded
+			);
+			assert_ok!(TemplateModule::server_key_retrieved(Origin::signed(2), 7, Public::from_low_u64_be(1), 1));
+			assert_eq!(TemplateModule::pending_requests(), vec![request]);
+			assert_eq!(TemplateModule::server_key(7), None);
+
+			assert_ok!(TemplateModule::server_key_retrieved(Origin::signed(3), 7, Public::from_low_u64_be(1), 1));
+			assert!(TemplateModule::pending_requests().is_empty());
+			assert_eq!(TemplateModule::server_key(7), Some(ServerKey { public: Public::from_low_u64_be(1), threshold: 1 }));
+			assert!(TemplateModule::request_responses(&PendingRequest::<u64>::ServerKeyRetrieval { document_id: 7 }.hash()).is_empty());
+
+			// stored key is never replaced
+			assert_noop!(TemplateModule::retrieve_server_key(Origin::signed(4), 7), Error::ServerKeyAlreadyGenerated);
+		});
+	}
+
+	#[test]
+	fn shadow_request_of_requester_without_access_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(2), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(2), 7, 3));
+			assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(3), 7, Public::from_low_u64_be(1)));
+			assert_ok!(TemplateModule::revoke_access(Origin::signed(2), 7, 3));
+
+			assert_ok!(TemplateModule::document_key_shadow_retrieved(
+				Origin::signed(1),
+				7,
+				3,
+				Default::default(),
+				Default::default(),
+				vec![]
+			));
+			assert!(TemplateModule::pending_requests().is_empty());
+			assert_eq!(TemplateModule::account_pending_requests(3), 0);
+		});
+	}
+
+	#[test]
+	fn admin_can_override_document_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+
+			assert_noop!(TemplateModule::force_revoke_access(Origin::signed(1), 7, 2), Error::Other("Invalid origin"));
+			assert_ok!(TemplateModule::force_revoke_access(Origin::ROOT, 7, 2));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+
+			assert_ok!(TemplateModule::force_delete_document(Origin::ROOT, 7));
+			assert!(!TemplateModule::permissions().has_document(7));
+			assert_noop!(TemplateModule::force_delete_document(Origin::ROOT, 7), Error::DocumentKeyIsNotFound);
+		});
+	}
+
+	#[test]
+	fn frozen_document_cannot_be_retrieved_or_changed() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+			assert_ok!(TemplateModule::freeze_document(Origin::ROOT, 7));
+
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Err(types::Error::KeyReleaseFrozen));
+			assert_noop!(TemplateModule::grant_access(Origin::signed(1), 7, 3), Error::DocumentFrozen);
+			assert_noop!(TemplateModule::delete_document(Origin::signed(1), 7), Error::DocumentFrozen);
+			assert_noop!(TemplateModule::unfreeze_document(Origin::signed(1), 7), Error::Other("Invalid origin"));
+
+			assert_ok!(TemplateModule::unfreeze_document(Origin::ROOT, 7));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Ok(vec![1, 2, 3]));
+		});
+	}
+
+	#[test]
+	fn admin_updates_server_set_parameters() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				TemplateModule::set_server_set_parameters(Origin::ROOT, None, Some(0), None),
+				Error::ZeroReportThreshold
+			);
+			assert_ok!(TemplateModule::set_server_set_parameters(Origin::ROOT, Some(500), None, Some(3)));
+			assert_eq!(TemplateModule::key_server_bond(), 500);
+			assert_eq!(TemplateModule::misbehaviour_report_threshold(), 2);
+			assert_eq!(TemplateModule::heartbeat_timeout(), 3);
+
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), key_pair().public().clone()));
+			assert_eq!(Balances::reserved_balance(1), 500);
+		});
+	}
+
+	fn sign_admin_call(nonce: u64, call: &Call<Test>, signers: &[&KeyPair]) -> Vec<Vec<u8>> {
+		let hash = TemplateModule::admin_call_hash(nonce, call);
+		signers.iter()
+			.map(|signer| parity_crypto::publickey::sign(signer.secret(), &hash).unwrap().to_vec())
+			.collect()
+	}
+
+	#[test]
+	fn admin_call_requires_threshold_of_signatures() {
+		with_externalities(&mut new_test_ext(), || {
+			let admins: Vec<_> = (0..3).map(|_| key_pair()).collect();
+			let policy = AdminPolicy::new(admins.iter().map(|admin| admin.public().clone()).collect(), 2).unwrap();
+			assert_ok!(TemplateModule::set_admin_policy(Origin::ROOT, Some(policy)));
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+
+			let call = Call::freeze_document(7);
+			assert_noop!(
+				TemplateModule::execute_admin_call(Origin::signed(5), Box::new(call.clone()), sign_admin_call(0, &call, &[&admins[0]])),
+				Error::NotEnoughAdminSignatures
+			);
+			assert_noop!(
+				TemplateModule::execute_admin_call(
+					Origin::signed(5),
+					Box::new(call.clone()),
+					sign_admin_call(0, &call, &[&admins[0], &admins[0]])
+				),
+				Error::NotEnoughAdminSignatures
+			);
+			assert_noop!(
+				TemplateModule::execute_admin_call(
+					Origin::signed(5),
+					Box::new(call.clone()),
+					sign_admin_call(0, &call, &[&admins[0], &key_pair()])
+				),
+				Error::NotEnoughAdminSignatures
+			);
+
+			let signatures = sign_admin_call(0, &call, &[&admins[0], &admins[2]]);
+			assert_ok!(TemplateModule::execute_admin_call(Origin::signed(5), Box::new(call.clone()), signatures.clone()));
+			assert!(TemplateModule::is_document_frozen(7));
+			assert_eq!(TemplateModule::admin_nonce(), 1);
+
+			// signatures can't be replayed once the nonce has changed
+			assert_noop!(
+				TemplateModule::execute_admin_call(Origin::signed(5), Box::new(call), signatures),
+				Error::NotEnoughAdminSignatures
+			);
+		});
+	}
+
+	#[test]
+	fn only_admin_operations_can_be_executed_with_signatures() {
+		with_externalities(&mut new_test_ext(), || {
+			let admin = key_pair();
+			let call = Call::do_something(42);
+			assert_noop!(
+				TemplateModule::execute_admin_call(Origin::signed(5), Box::new(call.clone()), sign_admin_call(0, &call, &[&admin])),
+				Error::NoAdminPolicy
+			);
+
+			assert_ok!(TemplateModule::set_admin_policy(Origin::ROOT, Some(AdminPolicy::single(admin.public().clone()))));
+			assert_noop!(
+				TemplateModule::execute_admin_call(Origin::signed(5), Box::new(call.clone()), sign_admin_call(0, &call, &[&admin])),
+				Error::NotAdminCall
+			);
+			assert_noop!(
+				TemplateModule::set_admin_policy(Origin::ROOT, Some(AdminPolicy { publics: vec![admin.public().clone()], threshold: 2 })),
+				Error::InvalidAdminPolicy
+			);
+		});
+	}
+
+	#[test]
+	fn global_freeze_halts_all_key_releases() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			let requester = key_pair();
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+			TestKeyServer::run_as(node.public().clone());
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(2), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(2), 7, requester.public().clone()));
+
+			assert_noop!(TemplateModule::freeze_key_releases(Origin::signed(2)), Error::Other("Invalid origin"));
+			assert_ok!(TemplateModule::freeze_key_releases(Origin::ROOT));
+
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Err(types::Error::KeyReleaseFrozen));
+			assert_noop!(
+				TemplateModule::retrieve_document_key_shadow(Origin::signed(2), 8, requester.public().clone()),
+				Error::KeyReleaseFrozen
+			);
+			TemplateModule::submit_pending_responses();
+			assert!(TestKeyServer::take_responses().is_empty());
+			assert_noop!(
+				TemplateModule::document_key_shadow_retrieved(
+					Origin::signed(1),
+					7,
+					2,
+					Default::default(),
+					Default::default(),
+					vec![]
+				),
+				Error::KeyReleaseFrozen
+			);
+
+			assert_ok!(TemplateModule::unfreeze_key_releases(Origin::ROOT));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&2, 7), Ok(vec![1, 2, 3]));
+			TemplateModule::submit_pending_responses();
+			for response in TestKeyServer::take_responses() {
+				assert_ok!(response.dispatch(Origin::signed(1)));
+			}
+			assert!(TemplateModule::pending_requests().is_empty());
+		});
+	}
+
+	#[test]
+	fn access_request_is_approved_or_rejected_by_the_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_noop!(TemplateModule::request_access(Origin::signed(2), 8, None), Error::DocumentKeyIsNotFound);
+			assert_noop!(TemplateModule::request_access(Origin::signed(1), 7, None), Error::AccessAlreadyGranted);
+
+			assert_ok!(TemplateModule::request_access(Origin::signed(2), 7, Some(MessageHash::from_low_u64_be(1))));
+			assert_ok!(TemplateModule::request_access(Origin::signed(3), 7, None));
+			assert_noop!(TemplateModule::request_access(Origin::signed(2), 7, None), Error::AccessAlreadyRequested);
+			assert_noop!(
+				TemplateModule::approve_access_request(Origin::signed(2), 7, 2),
+				Error::NotDocumentOwner
+			);
+
+			assert_ok!(TemplateModule::approve_access_request(Origin::signed(1), 7, 2));
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert_ok!(TemplateModule::reject_access_request(Origin::signed(1), 7, 3));
+			assert!(!TemplateModule::is_allowed(&3, 7));
+			assert_noop!(TemplateModule::approve_access_request(Origin::signed(1), 7, 3), Error::NoAccessRequest);
+			assert!(TemplateModule::access_request_expiries(ACCESS_REQUEST_TTL).is_empty());
+		});
+	}
+
+	#[test]
+	fn co_owned_document_access_request_is_approved_by_owners() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_co_owned_document_key(Origin::signed(1), 7, vec![1, 2, 3], vec![1, 2], 2));
+			assert_ok!(TemplateModule::request_access(Origin::signed(3), 7, None));
+
+			assert_ok!(TemplateModule::approve_access_request(Origin::signed(1), 7, 3));
+			assert!(!TemplateModule::is_allowed(&3, 7));
+			assert!(TemplateModule::access_request(&(7, 3)).is_some());
+			assert_eq!(
+				TemplateModule::proposal(&7, &0),
+				Some(Proposal { action: DocumentAction::Grant(3), approvals: vec![1], expires_at: 1 + PROPOSAL_TTL })
+			);
+
+			assert_ok!(TemplateModule::approve_proposal(Origin::signed(2), 7, 0));
+			assert!(TemplateModule::is_allowed(&3, 7));
+			assert!(TemplateModule::access_request(&(7, 3)).is_none());
+			assert!(TemplateModule::access_request_expiries(ACCESS_REQUEST_TTL).is_empty());
+		});
+	}
+
+	#[test]
+	fn co_owned_document_access_request_is_approved_with_single_proposal() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_co_owned_document_key(Origin::signed(1), 7, vec![1, 2, 3], vec![1, 2, 4], 3));
+			assert_ok!(TemplateModule::request_access(Origin::signed(3), 7, None));
+
+			assert_ok!(TemplateModule::approve_access_request(Origin::signed(1), 7, 3));
+			assert_noop!(TemplateModule::approve_access_request(Origin::signed(1), 7, 3), Error::AlreadyApproved);
+			assert_ok!(TemplateModule::approve_access_request(Origin::signed(2), 7, 3));
+			assert_eq!(TemplateModule::proposal_count(7), 1);
+			assert_eq!(
+				TemplateModule::proposal(&7, &0),
+				Some(Proposal { action: DocumentAction::Grant(3), approvals: vec![1, 2], expires_at: 1 + PROPOSAL_TTL })
+			);
+
+			// proposal is dropped along with the expired request
+			TemplateModule::on_finalize(1 + ACCESS_REQUEST_TTL);
+			assert!(TemplateModule::access_request(&(7, 3)).is_none());
+			assert_eq!(TemplateModule::proposal(&7, &0), None);
+			assert_eq!(TemplateModule::open_proposals(7), 0);
+			assert_noop!(TemplateModule::approve_proposal(Origin::signed(4), 7, 0), Error::ProposalNotFound);
+			assert!(!TemplateModule::is_allowed(&3, 7));
+		});
+	}
+
+	#[test]
+	fn access_request_expires() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::request_access(Origin::signed(2), 7, None));
+			assert!(TemplateModule::access_request(&(7, 2)).is_some());
+
+			TemplateModule::on_finalize(1 + ACCESS_REQUEST_TTL);
+			assert!(TemplateModule::access_request(&(7, 2)).is_none());
+			assert_noop!(TemplateModule::approve_access_request(Origin::signed(1), 7, 2), Error::NoAccessRequest);
+			assert_ok!(TemplateModule::request_access(Origin::signed(2), 7, None));
+		});
+	}
+
+	#[test]
+	fn co_owned_document_changes_require_owners_approval() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_noop!(
+				TemplateModule::store_co_owned_document_key(Origin::signed(1), 7, vec![1, 2, 3], vec![1, 2, 3], 4),
+				Error::InvalidApprovalThreshold
+			);
+			assert_ok!(TemplateModule::store_co_owned_document_key(Origin::signed(1), 7, vec![1, 2, 3], vec![1, 2, 3], 2));
+			assert_noop!(
+				TemplateModule::grant_access(Origin::signed(1), 7, 4),
+				Error::CoOwnedDocument
+			);
+			assert_noop!(
+				TemplateModule::propose_document_action(Origin::signed(4), 7, DocumentAction::Grant(4)),
+				Error::NotDocumentOwner
+			);
+
+			assert_ok!(TemplateModule::propose_document_action(Origin::signed(1), 7, DocumentAction::Grant(4)));
+			assert!(!TemplateModule::is_allowed(&4, 7));
+			assert_noop!(TemplateModule::approve_proposal(Origin::signed(1), 7, 0), Error::AlreadyApproved);
+			assert_ok!(TemplateModule::approve_proposal(Origin::signed(3), 7, 0));
+			assert!(TemplateModule::is_allowed(&4, 7));
+			assert_noop!(TemplateModule::approve_proposal(Origin::signed(2), 7, 0), Error::ProposalNotFound);
+
+			assert_ok!(TemplateModule::propose_document_action(Origin::signed(2), 7, DocumentAction::Delete));
+			assert!(TemplateModule::permissions().has_document(7));
+			assert_ok!(TemplateModule::approve_proposal(Origin::signed(1), 7, 1));
+			assert!(!TemplateModule::permissions().has_document(7));
+			assert_eq!(TemplateModule::proposal_count(7), 0);
+		});
+	}
+
+	#[test]
+	fn proposals_expire_and_are_weighed_by_document_removal() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_co_owned_document_key(Origin::signed(1), 7, vec![1, 2, 3], vec![1, 2, 3], 2));
+			let removal_weight = || Call::<Test>::force_delete_document(7).get_dispatch_info().weight;
+			let weight = removal_weight();
+
+			assert_ok!(TemplateModule::propose_document_action(Origin::signed(1), 7, DocumentAction::Grant(4)));
+			assert_ok!(TemplateModule::propose_document_action(Origin::signed(1), 7, DocumentAction::Revoke(3)));
+			assert_eq!(TemplateModule::open_proposals(7), 2);
+			assert_eq!(removal_weight(), weight + 2 * weights::DOCUMENT_REMOVAL.per_item);
+
+			TemplateModule::on_finalize(1 + PROPOSAL_TTL);
+			assert_eq!(TemplateModule::proposal(&7, &0), None);
+			assert_eq!(TemplateModule::open_proposals(7), 0);
+			assert_noop!(TemplateModule::approve_proposal(Origin::signed(2), 7, 0), Error::ProposalNotFound);
+			assert!(!TemplateModule::is_allowed(&4, 7));
+
+			assert_ok!(TemplateModule::propose_document_action(Origin::signed(1), 7, DocumentAction::Grant(4)));
+			assert_ok!(TemplateModule::force_delete_document(Origin::ROOT, 7));
+			assert_eq!(TemplateModule::proposal(&7, &2), None);
+			assert_eq!(TemplateModule::open_proposals(7), 0);
+		});
+	}
+
+	#[test]
+	fn time_locked_document_is_released_to_beneficiaries() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 3));
+			assert_noop!(
+				TemplateModule::set_release_schedule(Origin::signed(1), 7, 1, None),
+				Error::ReleaseInPast
+			);
+			assert_ok!(TemplateModule::set_release_schedule(Origin::signed(1), 7, 10, Some(vec![2])));
+			assert_noop!(
+				TemplateModule::set_release_schedule(Origin::signed(1), 7, 5, None),
+				Error::AlreadyTimeLocked
+			);
+
+			assert!(TemplateModule::is_allowed(&1, 7));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+			assert!(!TemplateModule::is_allowed(&3, 7));
+			assert_noop!(
+				TemplateModule::retrieve_document_key_shadow(Origin::signed(2), 7, key_pair().public().clone()),
+				Error::AccessDenied
+			);
+
+			System::set_block_number(10);
+			TemplateModule::on_initialize(10);
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert!(TemplateModule::is_allowed(&3, 7));
+			assert!(!TemplateModule::is_allowed(&4, 7));
+			assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(2), 7, key_pair().public().clone()));
+		});
+	}
+
+	#[test]
+	fn time_locked_document_without_beneficiaries_is_released_to_everyone() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::set_release_schedule(Origin::signed(1), 7, 10, None));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&4, 7), Err(types::Error::AccessDenied));
+
+			System::set_block_number(10);
+			assert_eq!(TemplateModule::get_encrypted_document_key(&4, 7), Ok(vec![1, 2, 3]));
+		});
+	}
+
+	#[test]
+	fn deleted_document_is_unscheduled() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::set_release_schedule(Origin::signed(1), 7, 10, None));
+			assert_eq!(TemplateModule::scheduled_releases(10), vec![7]);
+
+			assert_ok!(TemplateModule::delete_document(Origin::signed(1), 7));
+			assert!(TemplateModule::release_schedule(7).is_none());
+			assert!(TemplateModule::scheduled_releases(10).is_empty());
+		});
+	}
+
+	#[test]
+	fn beneficiaries_inherit_access_when_owner_stops_checking_in() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_noop!(
+				TemplateModule::set_inheritance(Origin::signed(1), 7, vec![2], 0),
+				Error::ZeroInactivityPeriod
+			);
+			assert_ok!(TemplateModule::set_inheritance(Origin::signed(1), 7, vec![2], 10));
+
+			System::set_block_number(11);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+			System::set_block_number(12);
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert!(!TemplateModule::is_allowed(&3, 7));
+
+			assert_ok!(TemplateModule::check_in(Origin::signed(1)));
+			assert_eq!(TemplateModule::last_check_in(&1), Some(12));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+
+			System::set_block_number(23);
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert_ok!(TemplateModule::remove_inheritance(Origin::signed(1), 7));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn emergency_access_is_granted_after_challenge_delay() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 7, vec![2]));
+			assert_noop!(TemplateModule::claim_emergency_access(Origin::signed(3), 7), Error::NotEmergencyAccount);
+
+			assert_ok!(TemplateModule::claim_emergency_access(Origin::signed(2), 7));
+			assert_noop!(
+				TemplateModule::complete_emergency_access(Origin::signed(2), 7),
+				Error::EmergencyAccessDelayNotPassed
+			);
+
+			System::set_block_number(1 + EMERGENCY_ACCESS_DELAY);
+			assert_ok!(TemplateModule::complete_emergency_access(Origin::signed(2), 7));
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert_eq!(
+				TemplateModule::audit_trail(7).into_iter().map(|entry| (entry.account, entry.block_number, entry.kind)).collect::<Vec<_>>(),
+				vec![
+					(2, 1, AuditKind::EmergencyAccessClaimed),
+					(2, 1 + EMERGENCY_ACCESS_DELAY, AuditKind::EmergencyAccessGranted),
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn emergency_claims_are_dropped_with_the_document() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 7, vec![2, 3]));
+			assert_ok!(TemplateModule::claim_emergency_access(Origin::signed(2), 7));
+			assert_ok!(TemplateModule::claim_emergency_access(Origin::signed(3), 7));
+
+			assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 7, vec![2]));
+			assert!(TemplateModule::emergency_claim(&(7, 2)).is_some());
+			assert!(TemplateModule::emergency_claim(&(7, 3)).is_none());
+
+			assert_ok!(TemplateModule::delete_document(Origin::signed(1), 7));
+			assert!(TemplateModule::emergency_claim(&(7, 2)).is_none());
+		});
+	}
+
+	#[test]
+	fn owner_vetoes_emergency_access() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 7, vec![2]));
+			assert_ok!(TemplateModule::claim_emergency_access(Origin::signed(2), 7));
+			assert_noop!(
+				TemplateModule::veto_emergency_access(Origin::signed(3), 7, 2),
+				Error::NotDocumentOwner
+			);
+			assert_ok!(TemplateModule::veto_emergency_access(Origin::signed(1), 7, 2));
+
+			System::set_block_number(EMERGENCY_ACCESS_DELAY);
+			assert_noop!(TemplateModule::complete_emergency_access(Origin::signed(2), 7), Error::NoEmergencyClaim);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+			assert_eq!(TemplateModule::audit_trail(7).last().map(|entry| entry.kind.clone()), Some(AuditKind::EmergencyAccessVetoed));
+		});
+	}
+
+	#[test]
+	fn purchased_access_is_paid_once_key_is_retrieved() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_noop!(TemplateModule::buy_access(Origin::signed(2), 7), Error::NotListed);
+			assert_ok!(TemplateModule::list_document(Origin::signed(1), 7, 300));
+
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert_eq!(Balances::reserved_balance(2), 300);
+			assert_noop!(TemplateModule::buy_access(Origin::signed(2), 7), Error::AccessAlreadyGranted);
+
+			// payment stays in escrow until the key servers deliver the key shadow
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(3), key_pair().public().clone()));
+			assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(2), 7, key_pair().public().clone()));
+			assert_eq!(Balances::reserved_balance(2), 300);
+			assert_ok!(TemplateModule::document_key_shadow_retrieved(
+				Origin::signed(3),
+				7,
+				2,
+				Default::default(),
+				Default::default(),
+				vec![]
+			));
+			assert_eq!(Balances::reserved_balance(2), 0);
+			assert_eq!(Balances::free_balance(2), 700);
+			assert_eq!(Balances::free_balance(1), 1_300);
+			assert_noop!(TemplateModule::confirm_purchase(Origin::signed(2), 7), Error::NoPendingPurchase);
+		});
+	}
+
+	#[test]
+	fn purchase_of_unusable_access_is_cancelled_by_the_buyer() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::list_document(Origin::signed(1), 7, 300));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert_noop!(TemplateModule::cancel_purchase(Origin::signed(2), 7), Error::PurchaseUsable);
+			assert_noop!(TemplateModule::cancel_purchase(Origin::signed(3), 7), Error::NoPendingPurchase);
+
+			assert_ok!(TemplateModule::freeze_document(Origin::ROOT, 7));
+			assert_ok!(TemplateModule::cancel_purchase(Origin::signed(2), 7));
+			assert_eq!(Balances::free_balance(2), 1_000);
+			assert_eq!(TemplateModule::document_buyers(7), Vec::<u64>::new());
+
+			assert_ok!(TemplateModule::unfreeze_document(Origin::ROOT, 7));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn purchase_is_refunded_when_document_is_rekeyed_or_deleted() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::list_document(Origin::signed(1), 7, 300));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(3), 7));
+			assert_ok!(TemplateModule::confirm_purchase(Origin::signed(3), 7));
+
+			assert_ok!(TemplateModule::rekey_document(Origin::signed(1), 7, vec![4, 5, 6]));
+			assert_eq!(Balances::free_balance(2), 1_000);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+			assert!(TemplateModule::is_allowed(&3, 7));
+			assert_eq!(TemplateModule::get_encrypted_document_key(&3, 7), Ok(vec![4, 5, 6]));
+
+			assert_ok!(TemplateModule::buy_access(Origin::signed(4), 7));
+			assert_ok!(TemplateModule::delete_document(Origin::signed(1), 7));
+			assert_eq!(Balances::free_balance(4), 1_000);
+			assert_eq!(Balances::free_balance(1), 1_300);
+			assert_eq!(TemplateModule::listing(7), None);
+		});
+	}
+
+	#[test]
+	fn purchase_is_refunded_when_access_is_revoked() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::list_document(Origin::signed(1), 7, 300));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(3), 7));
+
+			assert_ok!(TemplateModule::revoke_access(Origin::signed(1), 7, 2));
+			assert_eq!(Balances::reserved_balance(2), 0);
+			assert_eq!(TemplateModule::purchase((7, 2)), None);
+			assert_eq!(TemplateModule::document_buyers(7), vec![3]);
+
+			// access could be bought again, once the previous purchase is refunded
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert_eq!(Balances::reserved_balance(2), 300);
+			assert_eq!(TemplateModule::document_buyers(7), vec![3, 2]);
+
+			assert_ok!(TemplateModule::force_revoke_access(Origin::ROOT, 7, 3));
+			assert_eq!(Balances::reserved_balance(3), 0);
+			assert_eq!(TemplateModule::document_buyers(7), vec![2]);
+		});
+	}
+
+	#[test]
+	fn pending_purchase_is_refunded_when_expired_access_is_bought_again() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::list_document(Origin::signed(1), 7, 300));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert_ok!(TemplateModule::grant_temporary_access(Origin::signed(1), 7, 2, 5));
+
+			System::set_block_number(6);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+			assert_ok!(TemplateModule::buy_access(Origin::signed(2), 7));
+			assert_eq!(Balances::reserved_balance(2), 300);
+			assert_eq!(TemplateModule::document_buyers(7), vec![2]);
+		});
+	}
+
+	#[test]
+	fn temporary_access_expires() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_temporary_access(Origin::signed(1), 7, 2, 5));
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert_eq!(TemplateModule::permissions().access_expiry(&2, 7), Some(&5));
+
+			System::set_block_number(5);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert_eq!(TemplateModule::permissions().access_expiry(&2, 7), None);
+		});
+	}
+
+	#[test]
+	fn subscription_is_renewed_until_payment_fails() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 7, 400, 10));
+
+			assert_ok!(TemplateModule::subscribe(Origin::signed(2), 7));
+			assert_noop!(TemplateModule::subscribe(Origin::signed(2), 7), Error::AlreadySubscribed);
+			assert_eq!(Balances::free_balance(2), 600);
+			assert_eq!(TemplateModule::subscription(&(7, 2)).map(|subscription| subscription.renews_at), Some(11));
+			assert!(TemplateModule::is_allowed(&2, 7));
+
+			System::set_block_number(11);
+			TemplateModule::on_initialize(11);
+			assert_eq!(Balances::free_balance(2), 200);
+			assert_eq!(TemplateModule::subscription(&(7, 2)).map(|subscription| subscription.renews_at), Some(21));
+			assert!(TemplateModule::is_allowed(&2, 7));
+
+			System::set_block_number(21);
+			TemplateModule::on_initialize(21);
+			assert_eq!(Balances::free_balance(2), 200);
+			assert_eq!(Balances::free_balance(1), 1_800);
+			assert_eq!(TemplateModule::subscription(&(7, 2)), None);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn subscription_lapses_when_plan_is_changed() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 7, 100, 10));
+			assert_ok!(TemplateModule::subscribe(Origin::signed(2), 7));
+			assert_eq!(
+				TemplateModule::subscription(&(7, 2)).map(|subscription| subscription.plan),
+				Some(SubscriptionPlan { seller: 1, price: 100, period: 10 })
+			);
+
+			assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 7, 900, 10));
+			System::set_block_number(11);
+			TemplateModule::on_initialize(11);
+			assert_eq!(Balances::free_balance(2), 900);
+			assert_eq!(TemplateModule::subscription(&(7, 2)), None);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn subscription_does_not_affect_permanent_access() {
+		with_externalities(&mut new_test_ext(), || {
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 7, 400, 10));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+
+			assert_ok!(TemplateModule::subscribe(Origin::signed(2), 7));
+			assert_eq!(TemplateModule::permissions().access_expiry(&2, 7), None);
+			assert_ok!(TemplateModule::subscribe(Origin::signed(3), 7));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 3));
+
+			System::set_block_number(11);
+			TemplateModule::on_initialize(11);
+			System::set_block_number(21);
+			TemplateModule::on_initialize(21);
+			assert_eq!(TemplateModule::subscription(&(7, 2)), None);
+			assert_eq!(TemplateModule::subscription(&(7, 3)), None);
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert!(TemplateModule::is_allowed(&3, 7));
+		});
+	}
+
+	#[test]
+	fn unsubscribed_access_lasts_until_end_of_paid_period() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 7, 100, 10));
+			assert_ok!(TemplateModule::subscribe(Origin::signed(2), 7));
+			assert_ok!(TemplateModule::unsubscribe(Origin::signed(2), 7));
+			assert!(TemplateModule::is_allowed(&2, 7));
+
+			System::set_block_number(10);
+			TemplateModule::on_initialize(10);
+			assert_eq!(Balances::free_balance(2), 900);
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn key_retrievals_are_recorded_in_audit_trail() {
+		with_externalities(&mut new_test_ext(), || {
+			let node = key_pair();
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+			TestKeyServer::run_as(node.public().clone());
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(2), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(2), 7, 3));
+			assert_noop!(TemplateModule::retrieve_document_key(Origin::signed(4), 7), Error::AccessDenied);
+
+			System::set_block_number(1);
+			assert_ok!(TemplateModule::retrieve_document_key(Origin::signed(3), 7));
+			System::set_block_number(2);
+			assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(3), 7, key_pair().public().clone()));
+			TemplateModule::submit_pending_responses();
+			for response in TestKeyServer::take_responses() {
+				assert_ok!(response.dispatch(Origin::signed(1)));
+			}
+
+			assert_eq!(
+				TemplateModule::audit_trail(7),
+				vec![
+					AuditEntry { account: 3, block_number: 1, kind: AuditKind::DocumentKeyRetrieved },
+					AuditEntry { account: 3, block_number: 2, kind: AuditKind::DocumentKeyShadowRequested },
+					AuditEntry { account: 3, block_number: 2, kind: AuditKind::DocumentKeyShadowReleased },
+				]
+			);
+		});
+	}
+
+	#[test]
+	fn audit_trail_keeps_most_recent_records() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			for block_number in 1..=5 {
+				System::set_block_number(block_number);
+				assert_ok!(TemplateModule::retrieve_document_key(Origin::signed(1), 7));
+			}
+
+			let blocks: Vec<_> = TemplateModule::audit_trail(7).into_iter().map(|entry| entry.block_number).collect();
+			assert_eq!(blocks, vec![3, 4, 5]);
+		});
+	}
+
+	#[test]
+	fn batch_grant_and_revoke_change_all_permissions() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 8, vec![4, 5, 6]));
+			assert_ok!(TemplateModule::request_access(Origin::signed(3), 8, None));
+
+			assert_ok!(TemplateModule::batch_grant(Origin::signed(1), vec![7, 8], vec![2, 3]));
+			for &(document_id, account) in &[(7, 2), (7, 3), (8, 2), (8, 3)] {
+				assert!(TemplateModule::is_allowed(&account, document_id));
+			}
+			assert!(TemplateModule::access_request(&(8, 3)).is_none());
+			assert!(TemplateModule::access_request_expiries(ACCESS_REQUEST_TTL).is_empty());
+
+			assert_ok!(TemplateModule::batch_revoke(Origin::signed(1), vec![8], vec![2, 3]));
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert!(TemplateModule::is_allowed(&3, 7));
+			assert!(!TemplateModule::is_allowed(&2, 8));
+			assert!(!TemplateModule::is_allowed(&3, 8));
+		});
+	}
+
+	#[test]
+	fn batch_grant_is_atomic() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(2), 8, vec![4, 5, 6]));
+
+			assert_noop!(
+				TemplateModule::batch_grant(Origin::signed(1), vec![7, 8], vec![3]),
+				Error::NotDocumentOwner
+			);
+			assert!(!TemplateModule::is_allowed(&3, 7));
+		});
+	}
+
+	#[test]
+	fn batch_weight_grows_with_number_of_pairs() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+			let permissions_size = TemplateModule::permissions_size();
+			assert!(permissions_size > 0);
+
+			let weight = |document_ids: Vec<u64>, accounts: Vec<u64>|
+				Call::<Test>::batch_grant(document_ids, accounts).get_dispatch_info().weight;
+			assert_eq!(
+				weight(vec![1], vec![]),
+				weights::with_permissions(weights::BATCH_PERMISSIONS_CHANGE.base, permissions_size)
+			);
+			assert_eq!(
+				weight(vec![1, 2], vec![1, 2, 3]),
+				weights::with_permissions(weights::BATCH_PERMISSIONS_CHANGE.weigh(6), permissions_size)
+			);
+		});
+	}
+
+	#[test]
+	fn admin_call_weight_includes_weight_of_the_call() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			let permissions_size = TemplateModule::permissions_size();
+			assert!(permissions_size > 0);
+
+			let call = Call::<Test>::force_revoke_access(7, 2);
+			let call_weight = call.get_dispatch_info().weight;
+			assert_eq!(call_weight, weights::with_permissions(weights::ADMIN_OPERATION, permissions_size));
+			let admin_call = Call::<Test>::execute_admin_call(Box::new(call), vec![vec![0; 65]; 2]);
+			assert_eq!(
+				admin_call.get_dispatch_info().weight,
+				weights::ADMIN_CALL.weigh(2) + call_weight
+			);
+		});
+	}
+
+	#[test]
+	fn v0_permission_database_is_migrated() {
+		with_externalities(&mut new_test_ext(), || {
+			let database = database::v0::PermissionDatabase {
+				permission_entries: vec![
+					database::v0::PermissionEntry { allowed_account: 2, document_id: vec![7, 8] },
+					database::v0::PermissionEntry { allowed_account: 3, document_id: vec![8] },
+				],
+				documents: vec![
+					database::v0::Document { id: 7, key: vec![1, 2, 3] },
+					database::v0::Document { id: 8, key: vec![4, 5, 6] },
+				],
+			};
+			let key = <Permissions<Test> as support::storage::StorageValue<_>>::hashed_key();
+			support::storage::unhashed::put(&key, &database);
+			StorageVersion::put(0);
+
+			TemplateModule::on_initialize(1);
+
+			assert_eq!(TemplateModule::storage_version(), migration::CURRENT_STORAGE_VERSION);
+			let permissions = TemplateModule::permissions();
+			assert_eq!(permissions.document_key(7), Some(&vec![1, 2, 3]));
+			assert_eq!(permissions.document_key(8), Some(&vec![4, 5, 6]));
+			assert_eq!(permissions.document_owners(7), Some(&[][..]));
+			assert_eq!(permissions.document_owners(8), Some(&[][..]));
+			assert_eq!(TemplateModule::permissions_size(), permissions.size() as u32);
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert!(TemplateModule::is_allowed(&2, 8));
+			assert!(!TemplateModule::is_allowed(&3, 7));
+			assert!(TemplateModule::is_allowed(&3, 8));
+			assert_eq!(permissions.access_expiry(&3, 8), None);
+		});
+	}
+
+	#[test]
+	fn single_reader_of_v0_document_does_not_become_owner() {
+		with_externalities(&mut new_test_ext(), || {
+			let database = database::v0::PermissionDatabase {
+				permission_entries: vec![database::v0::PermissionEntry { allowed_account: 2, document_id: vec![7] }],
+				documents: vec![database::v0::Document { id: 7, key: vec![1, 2, 3] }],
+			};
+			let key = <Permissions<Test> as support::storage::StorageValue<_>>::hashed_key();
+			support::storage::unhashed::put(&key, &database);
+			StorageVersion::put(0);
+
+			TemplateModule::on_initialize(1);
+
+			assert!(TemplateModule::is_allowed(&2, 7));
+			assert!(!TemplateModule::permissions().is_document_owner(&2, 7));
+			assert_noop!(TemplateModule::grant_access(Origin::signed(2), 7, 3), Error::NotDocumentOwner);
+			assert_noop!(TemplateModule::delete_document(Origin::signed(2), 7), Error::NotDocumentOwner);
+			assert_ok!(TemplateModule::force_revoke_access(Origin::ROOT, 7, 2));
+			assert!(!TemplateModule::is_allowed(&2, 7));
+		});
+	}
+
+	#[test]
+	fn failed_migration_is_not_retried_and_blocks_dispatchables() {
+		with_externalities(&mut new_test_ext(), || {
+			let key = <Permissions<Test> as support::storage::StorageValue<_>>::hashed_key();
+			support::storage::unhashed::put_raw(&key, &[1, 2, 3]);
+			StorageVersion::put(0);
+
+			TemplateModule::on_initialize(1);
+			assert!(TemplateModule::migration_failed());
+			assert_eq!(TemplateModule::storage_version(), 0);
+			assert_noop!(
+				TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]),
+				Error::StorageNotMigrated
+			);
+
+			// even once the database becomes decodable
+			support::storage::unhashed::put(&key, &database::v0::PermissionDatabase::default());
+			TemplateModule::on_initialize(2);
+			assert_eq!(TemplateModule::storage_version(), 0);
+			assert_eq!(support::storage::unhashed::get_raw(&key), Some(vec![0, 0]));
+		});
+	}
+
+	#[test]
+	fn only_accounts_encoded_as_u64_are_converted() {
+		assert_eq!(migration::convert_account::<u64>(5), Some(5));
+		assert_eq!(migration::convert_account::<u32>(5), None);
+		assert_eq!(migration::convert_account::<[u8; 32]>(5), None);
+	}
+
+	#[test]
+	fn v0_permission_database_is_not_migrated_with_unconvertible_accounts() {
+		let database = database::v0::PermissionDatabase {
+			permission_entries: vec![
+				database::v0::PermissionEntry { allowed_account: 2, document_id: vec![7] },
+				database::v0::PermissionEntry { allowed_account: 3, document_id: vec![7] },
+			],
+			documents: vec![database::v0::Document { id: 7, key: vec![1, 2, 3] }],
+		};
+
+		let migrated: result::Result<PermissionDatabase<u64, u64>, u64> =
+			database.migrate(|account| Some(account).filter(|account| *account != 3));
+		assert_eq!(migrated, Err(3));
+	}
+
+	#[test]
+	fn current_storage_is_not_migrated() {
+		with_externalities(&mut new_test_ext(), || {
+			assert_eq!(TemplateModule::storage_version(), migration::CURRENT_STORAGE_VERSION);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, 2));
+			let permissions = TemplateModule::permissions();
+
+			TemplateModule::on_initialize(1);
+
+			assert_eq!(TemplateModule::permissions(), permissions);
+		});
+	}
+
+	#[test]
+	fn secret_store_errors_are_converted_to_module_errors() {
+		assert_eq!(Error::from(types::Error::AccessDenied), Error::AccessDenied);
+		assert_eq!(Error::from(types::Error::KeyReleaseFrozen), Error::KeyReleaseFrozen);
+		assert_eq!(Error::from(types::Error::ServerKeyIsNotFound), Error::ServerKeyIsNotFound);
+		assert_eq!(Error::from(types::Error::ConsensusUnreachable), Error::KeyServerError);
+	}
+
+	#[test]
+	fn address_requester_is_resolved_with_published_public() {
+		with_externalities(&mut new_test_ext(), || {
+			let key_pair = key_pair();
+			let requester = Requester::Address(key_pair.address());
+			let server_key_id = TemplateModule::server_key_id(7);
+			assert!(TemplateModule::requester_public(&requester, &server_key_id).is_err());
+
+			assert_noop!(
+				TemplateModule::publish_public(Origin::signed(1), key_pair.public().clone(), encryption_key_proof(2, &key_pair)),
+				Error::InvalidEncryptionKeyProof
+			);
+			let stranger = key_pair();
+			assert_noop!(
+				TemplateModule::publish_public(Origin::signed(1), key_pair.public().clone(), encryption_key_proof(1, &stranger)),
+				Error::InvalidEncryptionKeyProof
+			);
+			assert_ok!(TemplateModule::publish_public(Origin::signed(1), key_pair.public().clone(), encryption_key_proof(1, &key_pair)));
+			assert_noop!(
+				TemplateModule::publish_public(Origin::signed(2), key_pair.public().clone(), encryption_key_proof(2, &key_pair)),
+				Error::PublicAlreadyPublished
+			);
+
+			assert_eq!(TemplateModule::requester_public(&requester, &server_key_id), Ok(key_pair.public().clone()));
+			assert_eq!(TemplateModule::address_public(&key_pair.address()), Some(key_pair.public().clone()));
+		});
+	}
+
+	#[test]
+	fn requests_are_accepted_once_with_signed_envelopes() {
+		with_externalities(&mut new_test_ext(), || {
+			let key_pair = key_pair();
+			let server_key_id = TemplateModule::server_key_id(7);
+			let request = types::SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+			let signature = parity_crypto::publickey::sign(key_pair.secret(), &request.hash()).unwrap();
+			let requester = Requester::Request(request, RequestSignature::from_slice(&signature[..]));
+			let mut cache = ReplayCache::new(60);
+
+			assert_eq!(
+				TemplateModule::accept_requester(&mut cache, &requester, 7, 50),
+				Err(types::Error::DocumentKeyIsNotFound)
+			);
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+			assert_eq!(TemplateModule::accept_requester(&mut cache, &requester, 7, 50), Ok(()));
+			assert_eq!(
+				TemplateModule::accept_requester(&mut cache, &requester, 7, 60),
+				Err(types::Error::ReplayProtection)
+			);
+
+			let signature = parity_crypto::publickey::sign(key_pair.secret(), &server_key_id).unwrap();
+			let requester = Requester::Signature(RequestSignature::from_slice(&signature[..]));
+			assert_eq!(
+				TemplateModule::accept_requester(&mut cache, &requester, 7, 50),
+				Err(types::Error::ReplayProtection)
+			);
+		});
+	}
+
+	#[test]
+	fn typed_data_requests_are_bound_to_the_module_domain() {
+		with_externalities(&mut new_test_ext(), || {
+			let key_pair = key_pair();
+			let server_key_id = TemplateModule::server_key_id(7);
+			let request = types::SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+			let typed_data_request = |domain: TypedDataDomain| {
+				let signature = parity_crypto::publickey::sign(key_pair.secret(), &request.typed_data_hash(&domain)).unwrap();
+				Requester::TypedDataRequest(request.clone(), domain, RequestSignature::from_slice(&signature[..]))
+			};
+			let mut other_domain = TemplateModule::typed_data_domain();
+			other_domain.chain_id = Default::default();
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+
+			let requester = typed_data_request(TemplateModule::typed_data_domain());
+			assert_eq!(TemplateModule::requester_public(&requester, &server_key_id), Ok(key_pair.public().clone()));
+			let requester = typed_data_request(other_domain);
+			assert!(TemplateModule::requester_public(&requester, &server_key_id).is_err());
+			let mut cache = ReplayCache::new(60);
+			assert!(TemplateModule::accept_requester(&mut cache, &requester, 7, 50).is_err());
+		});
+	}
+
+	#[test]
+	fn signed_substrate_requests_are_served_once_to_allowed_requesters() {
+		use primitives::{sr25519, Pair};
+
+		with_externalities(&mut new_test_ext(), || {
+			let pair = sr25519::Pair::from_string("//Alice", None).unwrap();
+			let account = TestRequesterAccount::convert(pair.public().0);
+			let server_key_id = TemplateModule::server_key_id(7);
+			let envelope = |nonce| {
+				let request = types::SignedRequest { server_key_id, nonce, expires_at: 100 };
+				let signature = pair.sign(&request.hash()[..]);
+				Requester::Sr25519Request(request, pair.public(), signature)
+			};
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 7, vec![1, 2, 3]));
+
+			// the request is accepted even if access is denied, so it can't be replayed once access is granted
+			assert_eq!(TemplateModule::serve_requester(&envelope(0), 7, 50), Err(types::Error::AccessDenied));
+			assert_ok!(TemplateModule::grant_access(Origin::signed(1), 7, account));
+			assert_eq!(TemplateModule::serve_requester(&envelope(0), 7, 60), Err(types::Error::ReplayProtection));
+			assert_eq!(TemplateModule::serve_requester(&envelope(1), 7, 60), Ok(()));
+			assert_eq!(TemplateModule::serve_requester(&envelope(1), 7, 60), Err(types::Error::ReplayProtection));
+
+			let requester = Requester::Sr25519Signature(pair.public(), pair.sign(&server_key_id[..]));
+			assert_eq!(TemplateModule::serve_requester(&requester, 7, 60), Err(types::Error::ReplayProtection));
+			assert!(TemplateModule::check_requester_access(&requester, 7).is_err());
+		});
+	}
+
+	fn encryption_key_proof(who: u64, key_pair: &KeyPair) -> Vec<u8> {
+		let hash = TemplateModule::encryption_key_proof_hash(&who);
+		parity_crypto::publickey::sign(key_pair.secret(), &hash).unwrap().to_vec()
+	}
+
+	#[test]
+	fn encryption_keys_require_proof_of_possession() {
+		with_externalities(&mut new_test_ext(), || {
+			let (phone, laptop, tablet) = (key_pair(), key_pair(), key_pair());
+			assert_noop!(
+				TemplateModule::add_encryption_key(Origin::signed(1), phone.public().clone(), encryption_key_proof(2, &phone)),
+				Error::InvalidEncryptionKeyProof
+			);
+
+			assert_ok!(TemplateModule::add_encryption_key(Origin::signed(1), phone.public().clone(), encryption_key_proof(1, &phone)));
+			assert_ok!(TemplateModule::add_encryption_key(Origin::signed(1), laptop.public().clone(), encryption_key_proof(1, &laptop)));
+			assert_noop!(
+				TemplateModule::add_encryption_key(Origin::signed(1), tablet.public().clone(), encryption_key_proof(1, &tablet)),
+				Error::TooManyEncryptionKeys
+			);
+
+			assert_eq!(TemplateModule::encryption_keys(1), vec![phone.public().clone(), laptop.public().clone()]);
+			assert_eq!(TemplateModule::address_public(&laptop.address()), Some(laptop.public().clone()));
+		});
+	}
+
+	#[test]
+	fn encryption_keys_could_be_rotated_and_removed() {
+		with_externalities(&mut new_test_ext(), || {
+			let (old, new) = (key_pair(), key_pair());
+			assert_ok!(TemplateModule::add_encryption_key(Origin::signed(1), old.public().clone(), encryption_key_proof(1, &old)));
+
+			assert_noop!(
+				TemplateModule::rotate_encryption_key(
+					Origin::signed(1),
+					old.public().clone(),
+					new.public().clone(),
+					encryption_key_proof(1, &old),
+				),
+				Error::InvalidEncryptionKeyProof
+			);
+			assert_ok!(TemplateModule::rotate_encryption_key(
+				Origin::signed(1),
+				old.public().clone(),
+				new.public().clone(),
+				encryption_key_proof(1, &new),
+			));
+			assert_eq!(TemplateModule::encryption_keys(1), vec![new.public().clone()]);
+
+			assert_noop!(
+				TemplateModule::remove_encryption_key(Origin::signed(1), old.public().clone()),
+				Error::EncryptionKeyNotFound
+			);
+			assert_ok!(TemplateModule::remove_encryption_key(Origin::signed(1), new.public().clone()));
+			assert!(TemplateModule::encryption_keys(1).is_empty());
+		});
+	}
+
+	/// Runs of the dispatchables at the sizes of their variable-length arguments, covered by the `weights`
+	/// formulas. They are slow, so they are ignored by default: `cargo test --release -- --ignored benchmarks`.
+	mod benchmarks {
+		use super::*;
+
+		const SIZES: &[usize] = &[1, 10, 100, 1_000];
+
+		/// Run the call on the fresh state, prepared by `setup`, for every size. The name describes
+		/// the argument, which the size applies to.
+		fn bench(name: &str, setup: impl Fn(usize), call: impl Fn(usize) -> Result) {
+			for &size in SIZES {
+				with_externalities(&mut new_test_ext(), || {
+					setup(size);
+					assert!(call(size).is_ok(), "{} fails at size {}", name, size);
+				});
+			}
+		}
+
+		/// Store the documents 1..=count, owned by the account 1.
+		fn store_documents(count: usize) {
+			for document_id in 1..=count as u64 {
+				assert_ok!(TemplateModule::store_document_key(Origin::signed(1), document_id, vec![0; 64]));
+			}
+		}
+
+		/// Store the document 0, accessible by accounts 1..=count.
+		fn store_shared_document(count: usize) {
+			assert_ok!(TemplateModule::store_document_key(Origin::signed(1), 0, vec![0; 64]));
+			assert_ok!(TemplateModule::batch_grant(Origin::signed(1), vec![0], (1..=count as u64).collect()));
+		}
+
+		#[test]
+		#[ignore]
+		fn store_document_key() {
+			bench("store_document_key, per key byte", |_| (), |size| {
+				TemplateModule::store_document_key(Origin::signed(1), 0, vec![0; size])
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn store_co_owned_document_key() {
+			bench("store_co_owned_document_key, per owner", |_| (), |size| {
+				TemplateModule::store_co_owned_document_key(Origin::signed(1), 0, vec![0; 64], (1..=size as u64).collect(), 1)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn grant_access() {
+			bench("grant_access, per existing permission", store_shared_document, |size| {
+				TemplateModule::grant_access(Origin::signed(1), 0, size as u64 + 1)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn revoke_access() {
+			bench("revoke_access, per existing permission", store_shared_document, |size| {
+				TemplateModule::revoke_access(Origin::signed(1), 0, size as u64)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn batch_grant() {
+			bench("batch_grant, per (document, account) pair", |_| store_documents(1), |size| {
+				TemplateModule::batch_grant(Origin::signed(1), vec![1], (1..=size as u64).collect())
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn batch_revoke() {
+			bench("batch_revoke, per (document, account) pair", store_shared_document, |size| {
+				TemplateModule::batch_revoke(Origin::signed(1), vec![0], (1..=size as u64).collect())
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn set_emergency_accounts() {
+			bench("set_emergency_accounts, per account", |_| store_documents(1), |size| {
+				TemplateModule::set_emergency_accounts(Origin::signed(1), 1, (2..size as u64 + 2).collect())
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn retrieve_document_key_shadow() {
+			bench("retrieve_document_key_shadow, per existing permission", store_shared_document, |_| {
+				TemplateModule::retrieve_document_key_shadow(Origin::signed(1), 0, key_pair().public().clone())
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn document_key_shadow_retrieved() {
+			let node = key_pair();
+			bench("document_key_shadow_retrieved, per decrypt shadow", |_| {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(2), node.public().clone()));
+				store_documents(1);
+				assert_ok!(TemplateModule::retrieve_document_key_shadow(Origin::signed(1), 1, key_pair().public().clone()));
+			}, |size| {
+				TemplateModule::document_key_shadow_retrieved(
+					Origin::signed(2),
+					1,
+					1,
+					Public::zero(),
+					Public::zero(),
+					vec![vec![0; 64]; size],
+				)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn execute_admin_call() {
+			let admins: Vec<_> = (0..SIZES[SIZES.len() - 1]).map(|_| key_pair()).collect();
+			let call = Call::<Test>::freeze_document(1);
+			bench("execute_admin_call, per signature", |size| {
+				let publics = admins[..size].iter().map(|admin| admin.public().clone()).collect();
+				let policy = AdminPolicy::new(publics, size as u32).unwrap();
+				assert_ok!(TemplateModule::set_admin_policy(Origin::ROOT, Some(policy)));
+				store_documents(1);
+			}, |size| {
+				let signers: Vec<_> = admins[..size].iter().collect();
+				let signatures = sign_admin_call(0, &call, &signers);
+				TemplateModule::execute_admin_call(Origin::signed(1), Box::new(call.clone()), signatures)
+			});
+		}
+
+		/// Store the document 0, accessible by accounts 1..=count, and let the account `count + 1` pay.
+		fn store_shared_document_with_buyer(count: usize) -> u64 {
+			store_shared_document(count);
+			let buyer = count as u64 + 1;
+			Balances::make_free_balance_be(&buyer, 1_000);
+			buyer
+		}
+
+		#[test]
+		#[ignore]
+		fn do_something() {
+			bench("do_something", |_| (), |_| TemplateModule::do_something(Origin::signed(1), 42));
+		}
+
+		#[test]
+		#[ignore]
+		fn register_key_server() {
+			let node = key_pair();
+			bench("register_key_server", |_| (), |_| TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+		}
+
+		#[test]
+		#[ignore]
+		fn unregister_key_server() {
+			let node = key_pair();
+			bench("unregister_key_server", |_| {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+			}, |_| TemplateModule::unregister_key_server(Origin::signed(1)));
+		}
+
+		#[test]
+		#[ignore]
+		fn withdraw_bond() {
+			let node = key_pair();
+			bench("withdraw_bond", |_| {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+				assert_ok!(TemplateModule::unregister_key_server(Origin::signed(1)));
+				System::set_block_number(UNBONDING_PERIOD);
+			}, |_| TemplateModule::withdraw_bond(Origin::signed(1)));
+		}
+
+		#[test]
+		#[ignore]
+		fn report_misbehaviour() {
+			let (offender, reporter) = (key_pair(), key_pair());
+			let evidence = evidence_from(&offender);
+			bench("report_misbehaviour", |_| {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(1), offender.public().clone()));
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(2), reporter.public().clone()));
+			}, |_| TemplateModule::report_misbehaviour(Origin::signed(2), offender.public().clone(), evidence.clone()));
+		}
+
+		#[test]
+		#[ignore]
+		fn heartbeat() {
+			let node = key_pair();
+			let (heartbeat, signature) = signed_heartbeat(&node, 1);
+			bench("heartbeat", |_| {
+				assert_ok!(TemplateModule::register_key_server(Origin::signed(1), node.public().clone()));
+				System::set_block_number(1);
+			}, |_| TemplateModule::heartbeat(Origin::signed(1), heartbeat.clone(), signature.clone()));
+		}
+
+		#[test]
+		#[ignore]
+		fn delete_document() {
+			bench("delete_document, per existing permission", store_shared_document, |_| {
+				TemplateModule::delete_document(Origin::signed(1), 0)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn set_release_schedule() {
+			bench("set_release_schedule, per existing permission", store_shared_document, |_| {
+				TemplateModule::set_release_schedule(Origin::signed(1), 0, 10, None)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn set_inheritance() {
+			bench("set_inheritance, per existing permission", store_shared_document, |_| {
+				TemplateModule::set_inheritance(Origin::signed(1), 0, vec![2], 10)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn remove_inheritance() {
+			bench("remove_inheritance, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::set_inheritance(Origin::signed(1), 0, vec![2], 10));
+			}, |_| TemplateModule::remove_inheritance(Origin::signed(1), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn check_in() {
+			bench("check_in", |_| (), |_| TemplateModule::check_in(Origin::signed(1)));
+		}
+
+		#[test]
+		#[ignore]
+		fn claim_emergency_access() {
+			bench("claim_emergency_access", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 0, vec![2]));
+			}, |_| TemplateModule::claim_emergency_access(Origin::signed(2), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn veto_emergency_access() {
+			bench("veto_emergency_access, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 0, vec![2]));
+				assert_ok!(TemplateModule::claim_emergency_access(Origin::signed(2), 0));
+			}, |_| TemplateModule::veto_emergency_access(Origin::signed(1), 0, 2));
+		}
+
+		#[test]
+		#[ignore]
+		fn complete_emergency_access() {
+			bench("complete_emergency_access, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::set_emergency_accounts(Origin::signed(1), 0, vec![2]));
+				assert_ok!(TemplateModule::claim_emergency_access(Origin::signed(2), 0));
+				System::set_block_number(EMERGENCY_ACCESS_DELAY);
+			}, |_| TemplateModule::complete_emergency_access(Origin::signed(2), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn rekey_document() {
+			bench("rekey_document, per existing permission", store_shared_document, |_| {
+				TemplateModule::rekey_document(Origin::signed(1), 0, vec![0; 64])
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn list_document() {
+			bench("list_document, per existing permission", store_shared_document, |_| {
+				TemplateModule::list_document(Origin::signed(1), 0, 100)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn unlist_document() {
+			bench("unlist_document, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::list_document(Origin::signed(1), 0, 100));
+			}, |_| TemplateModule::unlist_document(Origin::signed(1), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn buy_access() {
+			bench("buy_access, per existing permission", |size| {
+				store_shared_document_with_buyer(size);
+				assert_ok!(TemplateModule::list_document(Origin::signed(1), 0, 100));
+			}, |size| TemplateModule::buy_access(Origin::signed(size as u64 + 1), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn confirm_purchase() {
+			bench("confirm_purchase", |size| {
+				let buyer = store_shared_document_with_buyer(size);
+				assert_ok!(TemplateModule::list_document(Origin::signed(1), 0, 100));
+				assert_ok!(TemplateModule::buy_access(Origin::signed(buyer), 0));
+			}, |size| TemplateModule::confirm_purchase(Origin::signed(size as u64 + 1), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn grant_temporary_access() {
+			bench("grant_temporary_access, per existing permission", store_shared_document, |size| {
+				TemplateModule::grant_temporary_access(Origin::signed(1), 0, size as u64 + 1, 10)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn offer_subscription() {
+			bench("offer_subscription, per existing permission", store_shared_document, |_| {
+				TemplateModule::offer_subscription(Origin::signed(1), 0, 100, 10)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn withdraw_subscription_offer() {
+			bench("withdraw_subscription_offer, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 0, 100, 10));
+			}, |_| TemplateModule::withdraw_subscription_offer(Origin::signed(1), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn subscribe() {
+			bench("subscribe, per existing permission", |size| {
+				store_shared_document_with_buyer(size);
+				assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 0, 100, 10));
+			}, |size| TemplateModule::subscribe(Origin::signed(size as u64 + 1), 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn unsubscribe() {
+			bench("unsubscribe", |size| {
+				let subscriber = store_shared_document_with_buyer(size);
+				assert_ok!(TemplateModule::offer_subscription(Origin::signed(1), 0, 100, 10));
+				assert_ok!(TemplateModule::subscribe(Origin::signed(subscriber), 0));
+			}, |size| TemplateModule::unsubscribe(Origin::signed(size as u64 + 1), 0));
+		}
+
+		/// Store the document 0, accessible by accounts 1..=count, and the document 1, co-owned by accounts 1 and 2.
+		fn store_co_owned_document(count: usize) {
+			store_shared_document(count);
+			assert_ok!(TemplateModule::store_co_owned_document_key(Origin::signed(1), 1, vec![0; 64], vec![1, 2], 2));
+		}
+
+		#[test]
+		#[ignore]
+		fn propose_document_action() {
+			bench("propose_document_action, per existing permission", store_co_owned_document, |_| {
+				TemplateModule::propose_document_action(Origin::signed(1), 1, DocumentAction::Grant(3))
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn approve_proposal() {
+			bench("approve_proposal, per existing permission", |size| {
+				store_co_owned_document(size);
+				assert_ok!(TemplateModule::propose_document_action(Origin::signed(1), 1, DocumentAction::Grant(3)));
+			}, |_| TemplateModule::approve_proposal(Origin::signed(2), 1, 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn request_access() {
+			bench("request_access, per existing permission", store_shared_document, |size| {
+				TemplateModule::request_access(Origin::signed(size as u64 + 1), 0, None)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn approve_access_request() {
+			bench("approve_access_request, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::request_access(Origin::signed(size as u64 + 1), 0, None));
+			}, |size| TemplateModule::approve_access_request(Origin::signed(1), 0, size as u64 + 1));
+		}
+
+		#[test]
+		#[ignore]
+		fn reject_access_request() {
+			bench("reject_access_request, per existing permission", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::request_access(Origin::signed(size as u64 + 1), 0, None));
+			}, |size| TemplateModule::reject_access_request(Origin::signed(1), 0, size as u64 + 1));
+		}
+
+		#[test]
+		#[ignore]
+		fn publish_public() {
+			let key = key_pair();
+			let proof = encryption_key_proof(1, &key);
+			bench("publish_public", |_| (), |_| TemplateModule::publish_public(Origin::signed(1), key.public().clone(), proof.clone()));
+		}
+
+		#[test]
+		#[ignore]
+		fn add_encryption_key() {
+			let key = key_pair();
+			let proof = encryption_key_proof(1, &key);
+			bench("add_encryption_key", |_| (), |_| {
+				TemplateModule::add_encryption_key(Origin::signed(1), key.public().clone(), proof.clone())
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn remove_encryption_key() {
+			let key = key_pair();
+			let proof = encryption_key_proof(1, &key);
+			bench("remove_encryption_key", |_| {
+				assert_ok!(TemplateModule::add_encryption_key(Origin::signed(1), key.public().clone(), proof.clone()));
+			}, |_| TemplateModule::remove_encryption_key(Origin::signed(1), key.public().clone()));
+		}
+
+		#[test]
+		#[ignore]
+		fn rotate_encryption_key() {
+			let (old_key, new_key) = (key_pair(), key_pair());
+			let (old_proof, new_proof) = (encryption_key_proof(1, &old_key), encryption_key_proof(1, &new_key));
+			bench("rotate_encryption_key", |_| {
+				assert_ok!(TemplateModule::add_encryption_key(Origin::signed(1), old_key.public().clone(), old_proof.clone()));
+			}, |_| {
+				TemplateModule::rotate_encryption_key(
+					Origin::signed(1),
+					old_key.public().clone(),
+					new_key.public().clone(),
+					new_proof.clone(),
+				)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn retrieve_document_key() {
+			bench("retrieve_document_key, per existing permission", store_shared_document, |_| {
+				TemplateModule::retrieve_document_key(Origin::signed(1), 0)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn generate_server_key() {
+			bench("generate_server_key, per existing permission", store_shared_document, |_| {
+				TemplateModule::generate_server_key(Origin::signed(1), 0, 1)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn retrieve_server_key() {
+			bench("retrieve_server_key, per existing permission", store_shared_document, |_| {
+				TemplateModule::retrieve_server_key(Origin::signed(1), 0)
+			});
+		}
+
+		/// Store the document 0, accessible by accounts 1..=count, and register the key server of the account 2.
+		fn store_shared_document_with_key_server(count: usize, node: &KeyPair) {
+			store_shared_document(count);
+			assert_ok!(TemplateModule::register_key_server(Origin::signed(2), node.public().clone()));
+		}
+
+		#[test]
+		#[ignore]
+		fn server_key_generated() {
+			let node = key_pair();
+			bench("server_key_generated", |size| {
+				store_shared_document_with_key_server(size, &node);
+				assert_ok!(TemplateModule::generate_server_key(Origin::signed(1), 0, 1));
+			}, |_| TemplateModule::server_key_generated(Origin::signed(2), 0, Public::zero()));
+		}
+
+		#[test]
+		#[ignore]
+		fn server_key_retrieved() {
+			let node = key_pair();
+			bench("server_key_retrieved", |size| {
+				store_shared_document_with_key_server(size, &node);
+				assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(1), 0));
+			}, |_| TemplateModule::server_key_retrieved(Origin::signed(2), 0, Public::zero(), 1));
+		}
+
+		#[test]
+		#[ignore]
+		fn request_failed() {
+			let node = key_pair();
+			bench("request_failed", |size| {
+				store_shared_document_with_key_server(size, &node);
+				assert_ok!(TemplateModule::retrieve_server_key(Origin::signed(1), 0));
+			}, |_| TemplateModule::request_failed(Origin::signed(2), PendingRequest::ServerKeyRetrieval { document_id: 0 }));
+		}
+
+		#[test]
+		#[ignore]
+		fn force_revoke_access() {
+			bench("force_revoke_access, per existing permission", store_shared_document, |size| {
+				TemplateModule::force_revoke_access(Origin::ROOT, 0, size as u64)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn force_delete_document() {
+			bench("force_delete_document, per existing permission", store_shared_document, |_| {
+				TemplateModule::force_delete_document(Origin::ROOT, 0)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn freeze_document() {
+			bench("freeze_document, per existing permission", store_shared_document, |_| {
+				TemplateModule::freeze_document(Origin::ROOT, 0)
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn unfreeze_document() {
+			bench("unfreeze_document", |size| {
+				store_shared_document(size);
+				assert_ok!(TemplateModule::freeze_document(Origin::ROOT, 0));
+			}, |_| TemplateModule::unfreeze_document(Origin::ROOT, 0));
+		}
+
+		#[test]
+		#[ignore]
+		fn set_server_set_parameters() {
+			bench("set_server_set_parameters", |_| (), |_| {
+				TemplateModule::set_server_set_parameters(Origin::ROOT, Some(KEY_SERVER_BOND), Some(2), Some(HEARTBEAT_TIMEOUT))
+			});
+		}
+
+		#[test]
+		#[ignore]
+		fn freeze_key_releases() {
+			bench("freeze_key_releases", |_| (), |_| TemplateModule::freeze_key_releases(Origin::ROOT));
+		}
+
+		#[test]
+		#[ignore]
+		fn unfreeze_key_releases() {
+			bench("unfreeze_key_releases", |_| {
+				assert_ok!(TemplateModule::freeze_key_releases(Origin::ROOT));
+			}, |_| TemplateModule::unfreeze_key_releases(Origin::ROOT));
+		}
+
+		#[test]
+		#[ignore]
+		fn set_admin_policy() {
+			let admin = key_pair();
+			bench("set_admin_policy", |_| (), |_| {
+				TemplateModule::set_admin_policy(Origin::ROOT, Some(AdminPolicy::single(admin.public().clone())))
+			});
+		}
 	}
 }
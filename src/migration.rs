@@ -0,0 +1,67 @@
+//! Storage migrations of the secret store module.
+//!
+//! `decl_module!` of the used substrate revision has no runtime upgrade hook, so `on_runtime_upgrade`
+//! is called at the start of the block while the storage version is outdated. Dispatchables are rejected
+//! until the storage is migrated. Failed migration is not retried: the runtime, which is able to migrate
+//! the storage, must clear `MigrationFailed`.
+
+use codec::{Encode, Decode};
+use support::storage::{unhashed, StorageValue};
+use crate::{Trait, StorageVersion, MigrationFailed, Permissions, PermissionsSize};
+use crate::database::v0;
+
+/// Version of the storage layout, used by this code.
+pub const CURRENT_STORAGE_VERSION: u32 = 1;
+
+/// Migrate the storage from the layout of any previous version.
+pub fn on_runtime_upgrade<T: Trait>() {
+	let version = StorageVersion::get();
+	if version >= CURRENT_STORAGE_VERSION || MigrationFailed::get() {
+		return;
+	}
+
+	if version == 0 && !migrate_v0_to_v1::<T>() {
+		MigrationFailed::put(true);
+		return;
+	}
+
+	StorageVersion::put(CURRENT_STORAGE_VERSION);
+}
+
+/// Convert the account, stored as `u64`. The conversion is only accepted if the account is decoded from
+/// the whole `u64` encoding and is encoded back to the same bytes.
+pub fn convert_account<AccountId: Encode + Decode>(account: u64) -> Option<AccountId> {
+	let encoded = account.encode();
+	let mut input = &encoded[..];
+	let converted = AccountId::decode(&mut input).ok()?;
+	Some(converted).filter(|converted| input.is_empty() && converted.encode() == encoded)
+}
+
+/// Documents got owners and permissions got expiries. Legacy documents are left without owners, with
+/// the permissions to retrieve them intact. Returns false and leaves the storage untouched if the database
+/// can't be decoded or some account can't be converted.
+fn migrate_v0_to_v1<T: Trait>() -> bool {
+	let key = <Permissions<T> as StorageValue<_>>::hashed_key();
+	let database = match unhashed::get_raw(&key) {
+		Some(encoded) => match v0::PermissionDatabase::decode(&mut &encoded[..]) {
+			Ok(database) => database,
+			Err(_) => {
+				runtime_io::print("failed to migrate secret store permissions: database can't be decoded");
+				return false;
+			},
+		},
+		None => return true,
+	};
+
+	match database.migrate(convert_account::<T::AccountId>) {
+		Ok(database) => {
+			PermissionsSize::put(database.size() as u32);
+			<Permissions<T>>::put(database);
+			true
+		},
+		Err(_) => {
+			runtime_io::print("failed to migrate secret store permissions: account can't be converted");
+			false
+		},
+	}
+}
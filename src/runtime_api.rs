@@ -0,0 +1,32 @@
+//! Runtime API definition for the secret store module.
+
+use codec::Codec;
+use rstd::prelude::*;
+use crate::AuditEntry;
+use crate::types::{EncryptedDocumentKey, Error, Public, Requester, ServerKeyId, TypedDataDomain};
+
+client::decl_runtime_apis! {
+	/// The API to query the secret store module state.
+	pub trait SecretStoreApi<AccountId, BlockNumber> where AccountId: Codec, BlockNumber: Codec {
+		/// Retrieve the encrypted document key on behalf of the account.
+		/// Is subject to the same access checks as all other key release paths, but is not recorded in
+		/// the audit trail: runtime API calls can't change the state. Audited retrieval is made with the
+		/// `retrieve_document_key` dispatchable.
+		fn encrypted_document_key(who: AccountId, document_id: u64) -> Result<EncryptedDocumentKey, Error>;
+		/// Retained audit trail of the document: who has accessed it through the module dispatchables,
+		/// when and how. Reads through `encrypted_document_key` are not recorded.
+		fn audit_trail(document_id: u64) -> Vec<AuditEntry<AccountId, BlockNumber>>;
+		/// Public of the requester, which is used to encrypt data for it. Public of the address-only
+		/// requester is looked up in the publics, published on chain. Requests, served by the key server,
+		/// must also pass `serve_requester`.
+		fn requester_public(requester: Requester, server_key_id: ServerKeyId) -> Result<Public, Error>;
+		/// Accept the signed request of the document, made by the requester at `now` (in seconds), with the
+		/// replay cache of the local key server and check that the requester is allowed to retrieve the key.
+		/// Every signed request is accepted once.
+		fn serve_requester(requester: Requester, document_id: u64, now: u64) -> Result<(), Error>;
+		/// EIP-712 domain, typed data requests must be signed in.
+		fn typed_data_domain() -> TypedDataDomain;
+		/// Public keys, used to encrypt data for the account.
+		fn encryption_keys(who: AccountId) -> Vec<Public>;
+	}
+}
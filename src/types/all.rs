@@ -1,8 +1,17 @@
-use std::collections::BTreeMap;
-use parity_crypto::publickey::Address;
+use rstd::collections::{btree_map::BTreeMap, btree_set::BTreeSet};
+use rstd::prelude::*;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String};
+use crate::crypto::{self, Address};
+use codec::{Encode, Decode};
+use primitives::{sr25519, ed25519, crypto::UncheckedFrom};
+use sr_primitives::traits::Verify;
+#[cfg(feature = "std")]
+use serde::{Serialize, Deserialize};
+use super::Error;
 
 /// Node id.
-pub type NodeId = crypto::publickey::Public;
+pub type NodeId = crypto::Public;
 /// Server key id. When key is used to encrypt document, it could be document contents hash.
 pub type ServerKeyId = primitive_types::H256;
 /// Encrypted document key type.
@@ -12,12 +21,13 @@ pub type MessageHash = primitive_types::H256;
 /// Message signature.
 pub type EncryptedMessageSignature = bytes::Bytes;
 /// Request signature type.
-pub type RequestSignature = crypto::publickey::Signature;
+pub type RequestSignature = crypto::Signature;
 /// Public key type.
-pub use crypto::publickey::Public;
+pub use crypto::Public;
 
 /// Secret store configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct NodeAddress {
 	/// IP address.
 	pub address: String,
@@ -26,16 +36,18 @@ pub struct NodeAddress {
 }
 
 /// Contract address.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum ContractAddress {
 	/// Address is read from registry.
 	Registry,
 	/// Address is specified.
-	Address(crypto::publickey::Address),
+	Address(crypto::Address),
 }
 
 /// Secret store configuration
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct ServiceConfiguration {
 	/// HTTP listener address. If None, HTTP API is disabled.
 	pub listener_address: Option<NodeAddress>,
@@ -58,44 +70,261 @@ pub struct ServiceConfiguration {
 }
 
 /// Key server cluster configuration
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct ClusterConfiguration {
 	/// This node address.
 	pub listener_address: NodeAddress,
 	/// All cluster nodes addresses.
-	pub nodes: BTreeMap<crypto::publickey::Public, NodeAddress>,
+	pub nodes: BTreeMap<crypto::Public, NodeAddress>,
 	/// Key Server Set contract address. If None, servers from 'nodes' map are used.
 	pub key_server_set_contract_address: Option<ContractAddress>,
 	/// Allow outbound connections to 'higher' nodes.
 	/// This is useful for tests, but slower a bit for production.
 	pub allow_connecting_to_higher_nodes: bool,
-	/// Administrator public key.
-	pub admin_public: Option<Public>,
+	/// Administrators policy.
+	pub admin_policy: Option<AdminPolicy>,
 	/// Should key servers set change session should be started when servers set changes.
 	/// This will only work when servers set is configured using KeyServerSet contract.
 	pub auto_migrate_enabled: bool,
 }
 
+/// Administrators policy: admin request must be signed by at least `threshold` of `publics`.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct AdminPolicy {
+	/// Administrators public keys.
+	pub publics: Vec<Public>,
+	/// Number of administrators signatures, required to authorize the request.
+	pub threshold: u32,
+}
+
+impl AdminPolicy {
+	/// Create new M-of-N policy.
+	pub fn new(publics: Vec<Public>, threshold: u32) -> Result<Self, Error> {
+		let policy = AdminPolicy { publics, threshold };
+		policy.validate()?;
+		Ok(policy)
+	}
+
+	/// Check that administrators are distinct and the threshold is reachable. Policies, constructed
+	/// bypassing `new` (e.g. decoded or deserialized), must be validated before use.
+	pub fn validate(&self) -> Result<(), Error> {
+		let distinct_publics: BTreeSet<_> = self.publics.iter().collect();
+		if distinct_publics.len() != self.publics.len() {
+			return Err(Error::Internal("duplicate administrator public key".into()));
+		}
+		if self.threshold == 0 || self.threshold as usize > self.publics.len() {
+			return Err(Error::NotEnoughNodesForThreshold);
+		}
+
+		Ok(())
+	}
+
+	/// Create policy with a single administrator.
+	pub fn single(public: Public) -> Self {
+		AdminPolicy { publics: vec![public], threshold: 1 }
+	}
+
+	/// Check that the message has been signed by at least `threshold` distinct administrators.
+	pub fn verify(&self, message_hash: &MessageHash, signatures: &[RequestSignature]) -> Result<(), Error> {
+		self.validate()?;
+
+		let signers: BTreeSet<_> = signatures.iter()
+			.filter_map(|signature| crypto::recover(signature, message_hash).ok())
+			.filter(|public| self.publics.contains(public))
+			.collect();
+		if signers.len() < self.threshold as usize {
+			return Err(Error::AccessDenied);
+		}
+
+		Ok(())
+	}
+}
+
 /// Shadow decryption result.
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub struct EncryptedDocumentKeyShadow {
 	/// Decrypted secret point. It is partially decrypted if shadow decryption was requested.
-	pub decrypted_secret: crypto::publickey::Public,
+	pub decrypted_secret: crypto::Public,
 	/// Shared common point.
-	pub common_point: Option<crypto::publickey::Public>,
+	pub common_point: Option<crypto::Public>,
 	/// If shadow decryption was requested: shadow decryption coefficients, encrypted with requestor public.
 	pub decrypt_shadows: Option<Vec<Vec<u8>>>,
 }
 
 /// Requester identification data.
-#[derive(Debug, Clone)]
+#[derive(Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Debug, Serialize, Deserialize))]
 pub enum Requester {
 	/// Requested with server key id signature.
-	Signature(crypto::publickey::Signature),
+	Signature(crypto::Signature),
 	/// Requested with public key.
-	Public(crypto::publickey::Public),
+	Public(crypto::Public),
 	/// Requested with verified address.
 	Address(Address),
+	/// Requested with sr25519 signature of server key id.
+	Sr25519Signature(sr25519::Public, sr25519::Signature),
+	/// Requested with sr25519 public key.
+	Sr25519Public(sr25519::Public),
+	/// Requested with ed25519 signature of server key id.
+	Ed25519Signature(ed25519::Public, ed25519::Signature),
+	/// Requested with ed25519 public key.
+	Ed25519Public(ed25519::Public),
+	/// Requested with signature of the request envelope.
+	Request(
+		SignedRequest,
+		crypto::Signature,
+	),
+	/// Requested with `personal_sign` (EIP-191) signature of the request envelope hash.
+	PersonalSignature(
+		SignedRequest,
+		crypto::Signature,
+	),
+	/// Requested with EIP-712 typed data signature of the request envelope, made in the given domain.
+	TypedDataRequest(
+		SignedRequest,
+		TypedDataDomain,
+		crypto::Signature,
+	),
+	/// Requested with sr25519 signature of the request envelope.
+	Sr25519Request(
+		SignedRequest,
+		sr25519::Public,
+		sr25519::Signature,
+	),
+	/// Requested with ed25519 signature of the request envelope.
+	Ed25519Request(
+		SignedRequest,
+		ed25519::Public,
+		ed25519::Signature,
+	),
+}
+
+/// Request envelope, signed by the requester. Unlike the bare server key id signature, the signature of
+/// the envelope is only valid for a single request (see `ReplayCache`), made before the expiry.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct SignedRequest {
+	/// Server key id of the request.
+	pub server_key_id: ServerKeyId,
+	/// Nonce, unique for every request of the requester.
+	pub nonce: u64,
+	/// Unix timestamp (in seconds), after which the request is not accepted.
+	pub expires_at: u64,
+}
+
+/// EIP-712 signing domain of the request envelopes. Binds typed data signatures to the chain and the module.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TypedDataDomain {
+	/// Id of the chain, the requests are made to.
+	pub chain_id: primitive_types::H256,
+	/// Address of the module, serving the requests.
+	pub verifying_contract: Address,
+}
+
+impl SignedRequest {
+	/// EIP-712 type of the envelope.
+	const TYPED_DATA_TYPE: &'static [u8] = b"Request(bytes32 serverKeyId,uint64 nonce,uint64 expiresAt)";
+	/// EIP-712 type of the signing domain.
+	const TYPED_DATA_DOMAIN_TYPE: &'static [u8] =
+		b"EIP712Domain(string name,string version,uint256 chainId,address verifyingContract)";
+	/// Name of the EIP-712 signing domain.
+	const TYPED_DATA_DOMAIN_NAME: &'static [u8] = b"Secret Store";
+	/// Version of the EIP-712 signing domain.
+	const TYPED_DATA_DOMAIN_VERSION: &'static [u8] = b"1";
+
+	/// Hash of the envelope, which must be signed by the requester.
+	pub fn hash(&self) -> MessageHash {
+		runtime_io::blake2_256(&(&b"secret store request"[..], self).encode()).into()
+	}
+
+	/// EIP-712 hash of the envelope, which must be signed by the requester in the domain, using typed data.
+	pub fn typed_data_hash(&self, domain: &TypedDataDomain) -> MessageHash {
+		let mut domain_data = Vec::with_capacity(160);
+		domain_data.extend_from_slice(&runtime_io::keccak_256(Self::TYPED_DATA_DOMAIN_TYPE));
+		domain_data.extend_from_slice(&runtime_io::keccak_256(Self::TYPED_DATA_DOMAIN_NAME));
+		domain_data.extend_from_slice(&runtime_io::keccak_256(Self::TYPED_DATA_DOMAIN_VERSION));
+		domain_data.extend_from_slice(&domain.chain_id[..]);
+		domain_data.extend_from_slice(&[0u8; 12]);
+		domain_data.extend_from_slice(&domain.verifying_contract[..]);
+
+		let mut request = Vec::with_capacity(128);
+		request.extend_from_slice(&runtime_io::keccak_256(Self::TYPED_DATA_TYPE));
+		request.extend_from_slice(&self.server_key_id[..]);
+		request.extend_from_slice(&[0u8; 24]);
+		request.extend_from_slice(&self.nonce.to_be_bytes());
+		request.extend_from_slice(&[0u8; 24]);
+		request.extend_from_slice(&self.expires_at.to_be_bytes());
+
+		let mut data = Vec::with_capacity(66);
+		data.extend_from_slice(b"\x19\x01");
+		data.extend_from_slice(&runtime_io::keccak_256(&domain_data));
+		data.extend_from_slice(&runtime_io::keccak_256(&request));
+		runtime_io::keccak_256(&data).into()
+	}
+}
+
+/// Hash of the message, signed with `personal_sign` (EIP-191 version 0x45).
+pub fn personal_message_hash(message: &[u8]) -> MessageHash {
+	let mut data = format!("\x19Ethereum Signed Message:\n{}", message.len()).into_bytes();
+	data.extend_from_slice(message);
+	runtime_io::keccak_256(&data).into()
+}
+
+/// Recover public from the signature, produced by the Ethereum wallet. Wallets use 27 and 28 recovery ids.
+fn recover_wallet_signature(signature: &crypto::Signature, message_hash: &MessageHash) -> Result<Public, String> {
+	let mut raw_signature = [0u8; 65];
+	raw_signature.copy_from_slice(&signature[..]);
+	if raw_signature[64] >= 27 {
+		raw_signature[64] -= 27;
+	}
+
+	crypto::recover(&raw_signature.into(), message_hash)
+		.map_err(|e| format!("bad signature: {}", e))
+}
+
+/// Cache of the accepted signed requests, kept until they expire. Every request is accepted only once.
+#[derive(Debug, Default, Clone)]
+pub struct ReplayCache {
+	/// Max time (in seconds) between the request acceptance and its expiry.
+	max_lifetime: u64,
+	/// Accepted requests (encoded signer public and envelope hash) along with their expiry.
+	accepted: BTreeMap<(Vec<u8>, MessageHash), u64>,
+}
+
+impl ReplayCache {
+	/// Create the cache, accepting requests which expire in at most `max_lifetime` seconds.
+	pub fn new(max_lifetime: u64) -> Self {
+		ReplayCache { max_lifetime, accepted: BTreeMap::new() }
+	}
+
+	/// Verify the request, made at `now`. Requesters, which do not use signed envelopes, are rejected,
+	/// because their signatures could be replayed forever.
+	pub fn accept(&mut self, requester: &Requester, server_key_id: &ServerKeyId, now: u64) -> Result<(), Error> {
+		let (request, signer) = match *requester {
+			Requester::Request(ref request, _) | Requester::PersonalSignature(ref request, _) |
+				Requester::TypedDataRequest(ref request, ..) =>
+				(request, requester.public(server_key_id).map(|public| public.as_bytes().to_vec())),
+			Requester::Sr25519Request(ref request, ..) | Requester::Ed25519Request(ref request, ..) =>
+				(request, requester.account_public(server_key_id).map(|public| public.to_vec())),
+			_ => return Err(Error::ReplayProtection),
+		};
+		let signer = signer.map_err(Error::InsufficientRequesterData)?;
+
+		if request.expires_at <= now || request.expires_at - now > self.max_lifetime {
+			return Err(Error::ReplayProtection);
+		}
+
+		self.accepted.retain(|_, expires_at| *expires_at > now);
+		if self.accepted.insert((signer, request.hash()), request.expires_at).is_some() {
+			return Err(Error::ReplayProtection);
+		}
+
+		Ok(())
+	}
 }
 
 impl Default for Requester {
@@ -107,27 +336,127 @@ impl Default for Requester {
 impl Requester {
 	pub fn public(&self, server_key_id: &ServerKeyId) -> Result<Public, String> {
 		match *self {
-			Requester::Signature(ref signature) => crypto::publickey::recover(signature, server_key_id)
+			Requester::Signature(ref signature) => crypto::recover(signature, server_key_id)
 				.map_err(|e| format!("bad signature: {}", e)),
 			Requester::Public(ref public) => Ok(public.clone()),
 			Requester::Address(_) => Err("cannot recover public from address".into()),
+			Requester::Request(ref request, ref signature) => {
+				if request.server_key_id != *server_key_id {
+					return Err("request is signed for another server key".into());
+				}
+				crypto::recover(signature, &request.hash())
+					.map_err(|e| format!("bad signature: {}", e))
+			},
+			Requester::PersonalSignature(ref request, ref signature) => {
+				if request.server_key_id != *server_key_id {
+					return Err("request is signed for another server key".into());
+				}
+				recover_wallet_signature(signature, &personal_message_hash(&request.hash()[..]))
+			},
+			Requester::TypedDataRequest(ref request, ref domain, ref signature) => {
+				if request.server_key_id != *server_key_id {
+					return Err("request is signed for another server key".into());
+				}
+				recover_wallet_signature(signature, &request.typed_data_hash(domain))
+			},
+			Requester::Sr25519Signature(..) | Requester::Sr25519Public(_) | Requester::Sr25519Request(..) |
+				Requester::Ed25519Signature(..) | Requester::Ed25519Public(_) | Requester::Ed25519Request(..) =>
+				Err("cannot recover secp256k1 public from substrate requester".into()),
 		}
 	}
 
-	pub fn address(&self, server_key_id: &ServerKeyId) -> Result<crypto::publickey::Address, String> {
+	/// Substrate public of the requester, as used by the on-chain ACL. Signature, if any, must be
+	/// the signature of the server key id or of the request envelope.
+	pub fn account_public(&self, server_key_id: &ServerKeyId) -> Result<[u8; 32], String> {
+		Ok(match *self {
+			Requester::Sr25519Signature(ref public, ref signature) => {
+				if !signature.verify(&server_key_id[..], public) {
+					return Err("bad sr25519 signature".into());
+				}
+				public.0
+			},
+			Requester::Sr25519Public(ref public) => public.0,
+			Requester::Ed25519Signature(ref public, ref signature) => {
+				if !signature.verify(&server_key_id[..], public) {
+					return Err("bad ed25519 signature".into());
+				}
+				public.0
+			},
+			Requester::Ed25519Public(ref public) => public.0,
+			Requester::Sr25519Request(ref request, ref public, ref signature) => {
+				if request.server_key_id != *server_key_id {
+					return Err("request is signed for another server key".into());
+				}
+				if !signature.verify(&request.hash()[..], public) {
+					return Err("bad sr25519 signature".into());
+				}
+				public.0
+			},
+			Requester::Ed25519Request(ref request, ref public, ref signature) => {
+				if request.server_key_id != *server_key_id {
+					return Err("request is signed for another server key".into());
+				}
+				if !signature.verify(&request.hash()[..], public) {
+					return Err("bad ed25519 signature".into());
+				}
+				public.0
+			},
+			Requester::Signature(_) | Requester::Public(_) | Requester::Address(_) | Requester::Request(..) |
+				Requester::PersonalSignature(..) | Requester::TypedDataRequest(..) =>
+				return Err("secp256k1 requester has no substrate account".into()),
+		})
+	}
+
+	/// Substrate account of the requester, as used by the on-chain ACL.
+	pub fn account_id<AccountId: UncheckedFrom<[u8; 32]>>(&self, server_key_id: &ServerKeyId) -> Result<AccountId, String> {
+		self.account_public(server_key_id).map(AccountId::unchecked_from)
+	}
+
+	/// Substrate public of the requester, which has proven to own it by signing the request envelope.
+	/// Bare publics and server key id signatures are rejected, because anyone could present or replay them.
+	pub fn signed_account_public(&self, server_key_id: &ServerKeyId) -> Result<[u8; 32], String> {
+		match *self {
+			Requester::Sr25519Request(..) | Requester::Ed25519Request(..) => self.account_public(server_key_id),
+			_ => Err("substrate requester must sign the request envelope".into()),
+		}
+	}
+
+	/// EIP-712 domain of the requester signature. Must be checked against the domain of the chain,
+	/// because any domain is accepted by `public`.
+	pub fn typed_data_domain(&self) -> Option<&TypedDataDomain> {
+		match *self {
+			Requester::TypedDataRequest(_, ref domain, _) => Some(domain),
+			_ => None,
+		}
+	}
+
+	/// Public of the requester. Public of the address-only requester is resolved with `lookup`,
+	/// e.g. from the publics, registered on chain.
+	pub fn resolve_public<F>(&self, server_key_id: &ServerKeyId, lookup: F) -> Result<Public, String>
+		where F: FnOnce(&Address) -> Option<Public>
+	{
+		match *self {
+			Requester::Address(ref address) => lookup(address)
+				.filter(|public| crypto::public_to_address(public) == *address)
+				.ok_or_else(|| "public of the address is not registered".into()),
+			_ => self.public(server_key_id),
+		}
+	}
+
+	pub fn address(&self, server_key_id: &ServerKeyId) -> Result<crypto::Address, String> {
 		self.public(server_key_id)
-			.map(|p| crypto::publickey::public_to_address(&p))
+			.map(|p| crypto::public_to_address(&p))
 	}
 }
 
-impl From<crypto::publickey::Signature> for Requester {
-	fn from(signature: crypto::publickey::Signature) -> Requester {
+impl From<crypto::Signature> for Requester {
+	fn from(signature: crypto::Signature) -> Requester {
 		Requester::Signature(signature)
 	}
 }
 
-impl From<crypto::publickey::Public> for Requester {
-	fn from(public: crypto::publickey::Public) -> Requester {
+impl From<crypto::Public> for Requester {
+	fn from(public: crypto::Public) -> Requester {
 		Requester::Public(public)
 	}
 }
@@ -137,3 +466,284 @@ impl From<Address> for Requester {
 		Requester::Address(address)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use std::fmt::Debug;
+	use parity_crypto::publickey::{Generator, KeyPair, Random, Secret};
+	use primitives::Pair;
+
+	fn assert_round_trip<T>(value: T) where T: Encode + Decode + Serialize + for<'de> Deserialize<'de> + PartialEq + Debug {
+		assert_eq!(T::decode(&mut &value.encode()[..]).unwrap(), value);
+		let json = serde_json::to_string(&value).unwrap();
+		assert_eq!(serde_json::from_str::<T>(&json).unwrap(), value);
+	}
+
+	fn key_pair() -> KeyPair {
+		Random.generate().unwrap()
+	}
+
+	fn sign(secret: &Secret, message_hash: &MessageHash) -> Result<RequestSignature, parity_crypto::publickey::Error> {
+		parity_crypto::publickey::sign(secret, message_hash).map(|signature| RequestSignature::from_slice(&signature[..]))
+	}
+
+	fn node_address(port: u16) -> NodeAddress {
+		NodeAddress { address: "127.0.0.1".into(), port }
+	}
+
+	#[test]
+	fn requester_round_trip() {
+		let key_pair = key_pair();
+		let signature = sign(key_pair.secret(), &MessageHash::from_low_u64_be(1)).unwrap();
+		assert_eq!(Requester::Signature(signature.clone()).encode(), [&[0u8][..], &signature[..]].concat());
+		assert_round_trip(Requester::Signature(signature));
+		assert_round_trip(Requester::Public(key_pair.public().clone()));
+		assert_round_trip(Requester::Address(key_pair.address()));
+
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let sr25519 = sr25519::Pair::from_string("//Alice", None).unwrap();
+		assert_round_trip(Requester::Sr25519Signature(sr25519.public(), sr25519.sign(&server_key_id[..])));
+		assert_round_trip(Requester::Sr25519Public(sr25519.public()));
+		let ed25519 = ed25519::Pair::from_string("//Alice", None).unwrap();
+		assert_round_trip(Requester::Ed25519Signature(ed25519.public(), ed25519.sign(&server_key_id[..])));
+		assert_round_trip(Requester::Ed25519Public(ed25519.public()));
+
+		let request = SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+		let requester = Requester::Sr25519Request(request.clone(), sr25519.public(), sr25519.sign(&request.hash()[..]));
+		assert_eq!(requester.encode()[0], 10);
+		assert_round_trip(requester);
+		assert_round_trip(Requester::Ed25519Request(request.clone(), ed25519.public(), ed25519.sign(&request.hash()[..])));
+	}
+
+	#[test]
+	fn substrate_requester_is_mapped_to_account_id() {
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let other_key_id = ServerKeyId::from_low_u64_be(2);
+
+		let sr25519 = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let requester = Requester::Sr25519Signature(sr25519.public(), sr25519.sign(&server_key_id[..]));
+		assert_eq!(requester.account_id::<sr25519::Public>(&server_key_id), Ok(sr25519.public()));
+		assert!(requester.account_id::<sr25519::Public>(&other_key_id).is_err());
+
+		let ed25519 = ed25519::Pair::from_string("//Bob", None).unwrap();
+		let requester = Requester::Ed25519Signature(ed25519.public(), ed25519.sign(&server_key_id[..]));
+		assert_eq!(
+			requester.account_id::<sr25519::Public>(&server_key_id),
+			Ok(sr25519::Public::unchecked_from(ed25519.public().0))
+		);
+		assert!(requester.account_id::<sr25519::Public>(&other_key_id).is_err());
+
+		let requester = Requester::Public(key_pair().public().clone());
+		assert!(requester.account_id::<sr25519::Public>(&server_key_id).is_err());
+	}
+
+	#[test]
+	fn only_substrate_requesters_signing_envelope_are_authenticated() {
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let other_key_id = ServerKeyId::from_low_u64_be(2);
+		let sr25519 = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let ed25519 = ed25519::Pair::from_string("//Bob", None).unwrap();
+		let request = SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+
+		let requester = Requester::Sr25519Request(request.clone(), sr25519.public(), sr25519.sign(&request.hash()[..]));
+		assert_eq!(requester.signed_account_public(&server_key_id), Ok(sr25519.public().0));
+		assert!(requester.signed_account_public(&other_key_id).is_err());
+		let requester = Requester::Ed25519Request(request.clone(), ed25519.public(), ed25519.sign(&request.hash()[..]));
+		assert_eq!(requester.signed_account_public(&server_key_id), Ok(ed25519.public().0));
+		let requester = Requester::Ed25519Request(request.clone(), ed25519.public(), ed25519.sign(&server_key_id[..]));
+		assert!(requester.signed_account_public(&server_key_id).is_err());
+
+		let requester = Requester::Sr25519Signature(sr25519.public(), sr25519.sign(&server_key_id[..]));
+		assert!(requester.signed_account_public(&server_key_id).is_err());
+		let requester = Requester::Ed25519Signature(ed25519.public(), ed25519.sign(&server_key_id[..]));
+		assert!(requester.signed_account_public(&server_key_id).is_err());
+		assert!(Requester::Sr25519Public(sr25519.public()).signed_account_public(&server_key_id).is_err());
+		assert!(Requester::Ed25519Public(ed25519.public()).signed_account_public(&server_key_id).is_err());
+	}
+
+	#[test]
+	fn encrypted_document_key_shadow_round_trip() {
+		assert_round_trip(EncryptedDocumentKeyShadow {
+			decrypted_secret: key_pair().public().clone(),
+			common_point: Some(key_pair().public().clone()),
+			decrypt_shadows: Some(vec![vec![1, 2, 3], vec![4, 5, 6]]),
+		});
+		assert_round_trip(EncryptedDocumentKeyShadow {
+			decrypted_secret: key_pair().public().clone(),
+			common_point: None,
+			decrypt_shadows: None,
+		});
+	}
+
+	#[test]
+	fn configuration_round_trip() {
+		let (first, second) = (key_pair(), key_pair());
+		assert_round_trip(ServiceConfiguration {
+			listener_address: Some(node_address(8082)),
+			service_contract_address: Some(ContractAddress::Registry),
+			service_contract_srv_gen_address: Some(ContractAddress::Address(first.address())),
+			service_contract_srv_retr_address: None,
+			service_contract_doc_store_address: None,
+			service_contract_doc_sretr_address: None,
+			acl_check_contract_address: Some(ContractAddress::Registry),
+			cluster_config: ClusterConfiguration {
+				listener_address: node_address(8083),
+				nodes: vec![
+					(first.public().clone(), node_address(8083)),
+					(second.public().clone(), node_address(8084)),
+				].into_iter().collect(),
+				key_server_set_contract_address: None,
+				allow_connecting_to_higher_nodes: true,
+				admin_policy: Some(AdminPolicy::new(vec![first.public().clone(), second.public().clone()], 2).unwrap()),
+				auto_migrate_enabled: false,
+			},
+			cors: Some(vec!["*".into()]),
+		});
+	}
+
+	#[test]
+	fn invalid_admin_policy_verifies_nothing() {
+		let admin = key_pair();
+		let message_hash = MessageHash::from_low_u64_be(1);
+		let signature = sign(admin.secret(), &message_hash).unwrap();
+		assert!(AdminPolicy::single(admin.public().clone()).verify(&message_hash, &[signature.clone()]).is_ok());
+
+		let zero_threshold = AdminPolicy { publics: vec![admin.public().clone()], threshold: 0 };
+		assert_eq!(zero_threshold.verify(&message_hash, &[]), Err(Error::NotEnoughNodesForThreshold));
+		let unreachable_threshold = AdminPolicy { publics: vec![admin.public().clone()], threshold: 2 };
+		assert_eq!(unreachable_threshold.verify(&message_hash, &[signature]), Err(Error::NotEnoughNodesForThreshold));
+	}
+
+	fn signed_request(key_pair: &KeyPair, server_key_id: u64, nonce: u64, expires_at: u64) -> Requester {
+		let request = SignedRequest { server_key_id: ServerKeyId::from_low_u64_be(server_key_id), nonce, expires_at };
+		let signature = sign(key_pair.secret(), &request.hash()).unwrap();
+		Requester::Request(request, signature)
+	}
+
+	#[test]
+	fn signed_request_is_verified() {
+		let key_pair = key_pair();
+		let requester = signed_request(&key_pair, 1, 0, 100);
+		assert_round_trip(requester.clone());
+		assert_eq!(requester.public(&ServerKeyId::from_low_u64_be(1)), Ok(key_pair.public().clone()));
+		assert!(requester.public(&ServerKeyId::from_low_u64_be(2)).is_err());
+	}
+
+	#[test]
+	fn signed_request_is_accepted_once_before_expiry() {
+		let key_pair = key_pair();
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let mut cache = ReplayCache::new(60);
+
+		assert_eq!(cache.accept(&signed_request(&key_pair, 1, 0, 100), &server_key_id, 50), Ok(()));
+		assert_eq!(cache.accept(&signed_request(&key_pair, 1, 0, 100), &server_key_id, 60), Err(Error::ReplayProtection));
+		assert!(cache.accept(&signed_request(&key_pair, 1, 1, 100), &server_key_id, 60).is_ok());
+
+		assert_eq!(cache.accept(&signed_request(&key_pair, 1, 2, 100), &server_key_id, 100), Err(Error::ReplayProtection));
+		assert_eq!(cache.accept(&signed_request(&key_pair, 1, 3, 200), &server_key_id, 100), Err(Error::ReplayProtection));
+	}
+
+	#[test]
+	fn requester_without_envelope_is_not_accepted() {
+		let key_pair = key_pair();
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let mut cache = ReplayCache::new(60);
+
+		let requester = Requester::Signature(sign(key_pair.secret(), &server_key_id).unwrap());
+		assert_eq!(requester.public(&server_key_id), Ok(key_pair.public().clone()));
+		assert_eq!(cache.accept(&requester, &server_key_id, 50), Err(Error::ReplayProtection));
+		assert_eq!(cache.accept(&Requester::Public(key_pair.public().clone()), &server_key_id, 50), Err(Error::ReplayProtection));
+
+		let sr25519 = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let requester = Requester::Sr25519Signature(sr25519.public(), sr25519.sign(&server_key_id[..]));
+		assert_eq!(cache.accept(&requester, &server_key_id, 50), Err(Error::ReplayProtection));
+	}
+
+	#[test]
+	fn substrate_signed_request_is_accepted_once() {
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let sr25519 = sr25519::Pair::from_string("//Alice", None).unwrap();
+		let ed25519 = ed25519::Pair::from_string("//Alice", None).unwrap();
+		let request = SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+		let mut cache = ReplayCache::new(60);
+
+		let requester = Requester::Sr25519Request(request.clone(), sr25519.public(), sr25519.sign(&request.hash()[..]));
+		assert_eq!(cache.accept(&requester, &server_key_id, 50), Ok(()));
+		assert_eq!(cache.accept(&requester, &server_key_id, 60), Err(Error::ReplayProtection));
+
+		let requester = Requester::Ed25519Request(request.clone(), ed25519.public(), ed25519.sign(&request.hash()[..]));
+		assert_eq!(cache.accept(&requester, &server_key_id, 50), Ok(()));
+		let forged = Requester::Ed25519Request(request.clone(), ed25519.public(), ed25519.sign(&server_key_id[..]));
+		assert!(cache.accept(&forged, &server_key_id, 50).is_err());
+	}
+
+	/// Sign the hash the way Ethereum wallets do, with 27 or 28 recovery id.
+	fn wallet_sign(key_pair: &KeyPair, message_hash: &MessageHash) -> crypto::Signature {
+		let mut signature = [0u8; 65];
+		signature.copy_from_slice(&sign(key_pair.secret(), message_hash).unwrap()[..]);
+		signature[64] += 27;
+		signature.into()
+	}
+
+	fn typed_data_domain(chain_id: u64) -> TypedDataDomain {
+		TypedDataDomain {
+			chain_id: primitive_types::H256::from_low_u64_be(chain_id),
+			verifying_contract: Address::from_low_u64_be(2),
+		}
+	}
+
+	#[test]
+	fn wallet_hashes_match_reference_values() {
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		assert_eq!(
+			personal_message_hash(&server_key_id[..]),
+			"c9798da569c6ded6bd4b17373ef332b7c84d68cdec3f420f583dcd7b441ae31d".parse().unwrap()
+		);
+		assert_eq!(
+			SignedRequest { server_key_id, nonce: 2, expires_at: 3 }.typed_data_hash(&typed_data_domain(1)),
+			"49c2fe5488ebf0dc5378f897f951e4edc00c781f647df7181cce1f47f2b1f620".parse().unwrap()
+		);
+	}
+
+	#[test]
+	fn wallet_signatures_are_verified() {
+		let key_pair = key_pair();
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let other_key_id = ServerKeyId::from_low_u64_be(2);
+		let request = SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+
+		let signature = wallet_sign(&key_pair, &personal_message_hash(&request.hash()[..]));
+		let requester = Requester::PersonalSignature(request.clone(), signature);
+		assert_round_trip(requester.clone());
+		assert_eq!(requester.public(&server_key_id), Ok(key_pair.public().clone()));
+		assert_eq!(requester.address(&server_key_id), Ok(key_pair.address()));
+		assert!(requester.public(&other_key_id).is_err());
+		let signature = wallet_sign(&key_pair, &personal_message_hash(&server_key_id[..]));
+		let requester = Requester::PersonalSignature(request.clone(), signature);
+		assert_ne!(requester.public(&server_key_id), Ok(key_pair.public().clone()));
+
+		let signature = wallet_sign(&key_pair, &request.typed_data_hash(&typed_data_domain(1)));
+		let requester = Requester::TypedDataRequest(request.clone(), typed_data_domain(1), signature.clone());
+		assert_round_trip(requester.clone());
+		assert_eq!(requester.typed_data_domain(), Some(&typed_data_domain(1)));
+		assert_eq!(requester.public(&server_key_id), Ok(key_pair.public().clone()));
+		assert_eq!(requester.address(&server_key_id), Ok(key_pair.address()));
+		assert!(requester.public(&other_key_id).is_err());
+		let requester = Requester::TypedDataRequest(request.clone(), typed_data_domain(2), signature);
+		assert_ne!(requester.public(&server_key_id), Ok(key_pair.public().clone()));
+	}
+
+	#[test]
+	fn personal_signature_of_envelope_is_accepted_once() {
+		let key_pair = key_pair();
+		let server_key_id = ServerKeyId::from_low_u64_be(1);
+		let request = SignedRequest { server_key_id, nonce: 0, expires_at: 100 };
+		let signature = wallet_sign(&key_pair, &personal_message_hash(&request.hash()[..]));
+		let requester = Requester::PersonalSignature(request, signature);
+		let mut cache = ReplayCache::new(60);
+
+		assert_eq!(cache.accept(&requester, &server_key_id, 50), Ok(()));
+		assert_eq!(cache.accept(&requester, &server_key_id, 60), Err(Error::ReplayProtection));
+	}
+}
@@ -14,15 +14,21 @@
 // You should have received a copy of the GNU General Public License
 // along with Parity Ethereum.  If not, see <http://www.gnu.org/licenses/>.
 
-use std::fmt;
+use rstd::fmt;
+use rstd::prelude::*;
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::{String, ToString}};
+#[cfg(feature = "std")]
 use std::net;
+#[cfg(feature = "std")]
 use std::io::Error as IoError;
+#[cfg(feature = "std")]
 use serde::{Serialize, Deserialize};
-
-use crypto;
+use codec::{Encode, Decode};
 
 /// Secret store error.
-#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Encode, Decode)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
 pub enum Error {
 	/// Invalid node address has been passed.
 	InvalidNodeAddress,
@@ -71,6 +77,8 @@ pub enum Error {
 	ConsensusUnreachable,
 	/// Acl storage error.
 	AccessDenied,
+	/// Access denied, because release of the document key has been frozen by administrators.
+	KeyReleaseFrozen,
 	/// Can't start session, because exclusive session is active.
 	ExclusiveSessionActive,
 	/// Can't start exclusive session, because there are other active sessions.
@@ -110,7 +118,9 @@ impl Error {
 			// temporary (?) consensus problems, related to other non-fatal errors => restarting is probably (!) a solution
 			Error::ConsensusTemporaryUnreachable |
 			// exclusive session errors => waiting && restarting is a solution
-			Error::ExclusiveSessionActive | Error::HasActiveSessions => true,
+			Error::ExclusiveSessionActive | Error::HasActiveSessions |
+			// frozen key release => waiting for administrators to unfreeze && restarting is a solution
+			Error::KeyReleaseFrozen => true,
 
 			// fatal errors:
 
@@ -150,6 +160,7 @@ impl fmt::Display for Error {
 			Error::ConsensusUnreachable => write!(f, "Consensus unreachable"),
 			Error::ConsensusTemporaryUnreachable => write!(f, "Consensus temporary unreachable"),
 			Error::AccessDenied => write!(f, "Access denied"),
+			Error::KeyReleaseFrozen => write!(f, "Access denied: key release is frozen"),
 			Error::ExclusiveSessionActive => write!(f, "Exclusive session active"),
 			Error::HasActiveSessions => write!(f, "Unable to start exclusive session"),
 			Error::InsufficientRequesterData(ref e) => write!(f, "Insufficient requester data: {}", e),
@@ -163,18 +174,21 @@ impl fmt::Display for Error {
 	}
 }
 
-impl From<crypto::publickey::Error> for Error {
-	fn from(err: crypto::publickey::Error) -> Self {
+#[cfg(feature = "std")]
+impl From<parity_crypto::publickey::Error> for Error {
+	fn from(err: parity_crypto::publickey::Error) -> Self {
 		Error::EthKey(err.into())
 	}
 }
 
-impl From<crypto::Error> for Error {
-	fn from(err: crypto::Error) -> Self {
+#[cfg(feature = "std")]
+impl From<parity_crypto::Error> for Error {
+	fn from(err: parity_crypto::Error) -> Self {
 		Error::EthKey(err.to_string())
 	}
 }
 
+#[cfg(feature = "std")]
 impl From<IoError> for Error {
 	fn from(err: IoError) -> Self {
 		Error::Io(err.to_string())
@@ -187,6 +201,7 @@ impl Into<String> for Error {
 	}
 }
 
+#[cfg(feature = "std")]
 impl From<net::AddrParseError> for Error {
 	fn from(err: net::AddrParseError) -> Error {
 		Error::Internal(err.to_string())
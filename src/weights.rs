@@ -0,0 +1,120 @@
+//! Weights of the secret store module dispatchables.
+//!
+//! Weight of every dispatchable is the base cost plus the cost of every item of its variable-length
+//! arguments. Dispatchables, which read the permissions database, also pay for every item of the
+//! database, because the whole database is decoded (and re-encoded on change). Figures are estimates
+//! of the relative cost of the operations, not measurements: storage writes and signature checks
+//! are the most expensive, decoding of a single item is the cheapest.
+
+use support::weights::{Weight, WeighData, ClassifyDispatch, DispatchClass};
+
+/// Weight, computed from the call arguments by the function.
+pub struct WeightOf<F>(pub F);
+
+impl<Args, F: Fn(Args) -> Weight> WeighData<Args> for WeightOf<F> {
+	fn weigh_data(&self, args: Args) -> Weight {
+		(self.0)(args)
+	}
+}
+
+impl<Args, F> ClassifyDispatch<Args> for WeightOf<F> {
+	fn classify_dispatch(&self, _: Args) -> DispatchClass {
+		DispatchClass::Normal
+	}
+}
+
+/// Weight of the given class, computed from the call arguments by the function.
+pub struct ClassifiedWeightOf<F>(pub DispatchClass, pub F);
+
+impl<Args, F: Fn(Args) -> Weight> WeighData<Args> for ClassifiedWeightOf<F> {
+	fn weigh_data(&self, args: Args) -> Weight {
+		(self.1)(args)
+	}
+}
+
+impl<Args, F> ClassifyDispatch<Args> for ClassifiedWeightOf<F> {
+	fn classify_dispatch(&self, _: Args) -> DispatchClass {
+		self.0
+	}
+}
+
+/// Weight of the call, which reads the permissions database. Size of the database is read by the function.
+pub struct WithPermissions<F>(pub DispatchClass, pub Weight, pub F);
+
+impl<Args, F: Fn() -> u32> WeighData<Args> for WithPermissions<F> {
+	fn weigh_data(&self, _: Args) -> Weight {
+		with_permissions(self.1, (self.2)())
+	}
+}
+
+impl<Args, F> ClassifyDispatch<Args> for WithPermissions<F> {
+	fn classify_dispatch(&self, _: Args) -> DispatchClass {
+		self.0
+	}
+}
+
+/// Weight of the call plus the cost of decoding and encoding the permissions database of given size.
+pub fn with_permissions(weight: Weight, permissions_size: u32) -> Weight {
+	weight.saturating_add(PERMISSIONS_ITEM.saturating_mul(permissions_size as Weight))
+}
+
+/// Weight, linearly depending on the number of items.
+pub struct Linear {
+	/// Weight of the call itself.
+	pub base: Weight,
+	/// Weight of every item.
+	pub per_item: Weight,
+}
+
+impl Linear {
+	/// Weight of the call with given number of items.
+	pub fn weigh(&self, items: usize) -> Weight {
+		self.base.saturating_add(self.per_item.saturating_mul(items as Weight))
+	}
+}
+
+/// Storing the template value.
+pub const DO_SOMETHING: Weight = 10_000;
+/// Registering or unregistering the key server, or withdrawing its bond, including the bond reservation.
+pub const KEY_SERVER_MANAGEMENT: Weight = 100_000;
+/// Misbehaviour report, including the signature verification and the message check.
+pub const REPORT_MISBEHAVIOUR: Weight = 300_000;
+/// Heartbeat of the key server, including the signature recovery.
+pub const HEARTBEAT: Weight = 150_000;
+/// Liveness check at the start of the block, per key server which heartbeat deadline has passed.
+pub const HEARTBEAT_DEADLINES: Linear = Linear { base: 10_000, per_item: 50_000 };
+
+/// Decoding and encoding the permissions database, per document, permission entry or permitted document.
+pub const PERMISSIONS_ITEM: Weight = 1_000;
+
+/// Storing the document key, per byte of the key.
+pub const STORE_DOCUMENT_KEY: Linear = Linear { base: 50_000, per_item: 10 };
+/// Storing the co-owned document key, per pair of owners (owners are checked for duplicates).
+pub const STORE_CO_OWNED_DOCUMENT_KEY: Linear = Linear { base: 50_000, per_item: 1_000 };
+/// Granting or revoking access to the document, or deleting the document.
+pub const PERMISSIONS_CHANGE: Weight = 50_000;
+/// Batch permissions change, per (document, account) pair.
+pub const BATCH_PERMISSIONS_CHANGE: Linear = Linear { base: 50_000, per_item: 5_000 };
+/// Setting the list of accounts with access to the document, per account.
+pub const DOCUMENT_ACCOUNTS: Linear = Linear { base: 50_000, per_item: 5_000 };
+/// Any other operation on the document: time locks, proposals, access requests, listings.
+pub const DOCUMENT_OPERATION: Weight = 50_000;
+/// Deleting the document (directly or by executing the proposal), per open proposal of the document.
+pub const DOCUMENT_REMOVAL: Linear = Linear { base: 50_000, per_item: 10_000 };
+/// Operation on the document which moves funds: purchases and subscriptions.
+pub const DOCUMENT_PAYMENT: Weight = 100_000;
+
+/// Publishing the public key of the address, including the proof of possession check.
+pub const PUBLISH_PUBLIC: Weight = 150_000;
+/// Adding or rotating the encryption key of the account, including the proof of possession check.
+pub const ENCRYPTION_KEY_MANAGEMENT: Weight = 150_000;
+
+/// Request, submitted to the key servers.
+pub const KEY_SERVER_REQUEST: Weight = 100_000;
+/// Response of the key servers, per decrypt shadow.
+pub const KEY_SERVER_RESPONSE: Linear = Linear { base: 100_000, per_item: 10_000 };
+
+/// Administrative operation.
+pub const ADMIN_OPERATION: Weight = 50_000;
+/// Admin call execution, per signature of the administrator. Weight of the call itself is added on top.
+pub const ADMIN_CALL: Linear = Linear { base: 50_000, per_item: 150_000 };